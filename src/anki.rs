@@ -0,0 +1,369 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cloze;
+use crate::csv;
+use crate::diagnostics::Diagnostic;
+
+/// How an Anki "Notes in Plain Text" export is laid out, read from the `#key:value`
+/// lines at the top of the file
+struct Directives {
+    separator: char,
+    html: bool,                 // Fields hold HTML that needs stripping
+    tags_column: Option<usize>, // Field index of the note's tags
+    guid_column: Option<usize>, // Field index of the note's Anki ID
+    skip_columns: Vec<usize>,   // Notetype and deck columns, which have no card field
+    deck: Option<String>,       // Deck name, used to name the imported deck
+    tags: Vec<String>,          // Tags added to every note
+}
+
+impl Default for Directives {
+    fn default() -> Self {
+        Directives {
+            separator: '\t',
+            html: false,
+            tags_column: None,
+            guid_column: None,
+            skip_columns: Vec::new(),
+            deck: None,
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// A finished import: where the deck was written and what was left out
+pub struct Import {
+    pub path: String,
+    pub notes: usize,
+    pub problems: Vec<Diagnostic>,
+}
+
+/// Converts an Anki plain-text export into a deck in `folder`, named after
+/// its `#deck:` directive or else the export's filename. Refuses to
+/// overwrite a deck that already exists.
+pub fn import(export_path: &str, folder: &str) -> Result<Import, String> {
+    let text = fs::read_to_string(export_path).map_err(|e| format!("could not read {}: {}", export_path, e))?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+    let mut problems = Vec::new();
+
+    // Directives come first, one per line
+    let mut directives = Directives::default();
+    let mut header_lines = 0;
+    let mut body = text;
+    while let Some(directive) = body.strip_prefix('#') {
+        let (directive, rest) = directive.split_once('\n').unwrap_or((directive, ""));
+        header_lines += 1;
+        if let Err(reason) = apply_directive(&mut directives, directive.trim_end_matches('\r')) {
+            problems.push(Diagnostic::at(export_path, header_lines, 1, reason));
+        }
+        body = rest;
+    }
+
+    let records = csv::read_records(body, directives.separator)
+        .map_err(|e| Diagnostic::at(export_path, e.line + header_lines, e.column, e.reason).to_string())?;
+
+    let has_guids = directives.guid_column.is_some();
+    let mut header = vec!["question", "answer", "tags", "notes"];
+    if has_guids {
+        header.insert(0, "id");
+    }
+    let mut deck = csv::format_record(&header, ',') + "\n";
+    let mut notes = 0;
+
+    for record in records {
+        let (line, column) = record.positions[0];
+        let clean = |field: &str| if directives.html { strip_html(field) } else { field.to_string() };
+        let special = [directives.tags_column, directives.guid_column];
+        let content: Vec<String> = record
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !special.contains(&Some(*i)) && !directives.skip_columns.contains(i))
+            .map(|(_, field)| clean(field))
+            .collect();
+
+        let question = content.first().cloned().unwrap_or_default();
+        if question.is_empty() {
+            problems.push(Diagnostic::at(export_path, line + header_lines, column, "note has no front field, skipped"));
+            continue;
+        }
+        // Cloze notes keep everything after the text as notes; other notes
+        // have their answer in the second field
+        let is_cloze = !cloze::expand(&question).is_empty();
+        let extra_from = if is_cloze { 1 } else { 2 };
+        let answer = if is_cloze { String::new() } else { content.get(1).cloned().unwrap_or_default() };
+        if answer.is_empty() && !is_cloze {
+            problems.push(Diagnostic::at(export_path, line + header_lines, column, "note has no back field, skipped"));
+            continue;
+        }
+        let extra: Vec<&str> = content.iter().skip(extra_from).map(String::as_str).filter(|field| !field.is_empty()).collect();
+
+        let field = |index: Option<usize>| index.and_then(|i| record.fields.get(i)).map_or("", |field| field.as_str());
+        let mut tags: Vec<&str> = directives.tags.iter().map(String::as_str).collect();
+        tags.extend(field(directives.tags_column).split_whitespace());
+
+        let tags = tags.join(" ");
+        let extra = extra.join("\n");
+        let mut row = vec![question.as_str(), answer.as_str(), tags.as_str(), extra.as_str()];
+        if has_guids {
+            row.insert(0, field(directives.guid_column));
+        }
+        deck.push_str(&csv::format_record(&row, ','));
+        deck.push('\n');
+        notes += 1;
+    }
+
+    if notes == 0 {
+        return Err(format!("{} has no notes that can be imported", export_path));
+    }
+
+    let name = directives
+        .deck
+        .as_deref()
+        .map(deck_file_name)
+        .filter(|name| !name.is_empty())
+        .or_else(|| Path::new(export_path).file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "anki_import".to_string());
+    let path = Path::new(folder).join(format!("{}.csv", name));
+    if path.exists() {
+        return Err(format!("{} already exists; rename or remove it and import again", path.display()));
+    }
+    fs::create_dir_all(folder)
+        .and_then(|_| fs::write(&path, deck))
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))?;
+
+    Ok(Import {
+        path: path.display().to_string(),
+        notes,
+        problems,
+    })
+}
+
+/// Applies one `key:value` directive line (without its `#`)
+fn apply_directive(directives: &mut Directives, directive: &str) -> Result<(), String> {
+    let Some((key, value)) = directive.split_once(':') else {
+        return Err(format!("expected '#key:value', got '#{}'", directive));
+    };
+    let value = value.trim();
+    // Columns are counted from 1
+    let column = || match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n - 1),
+        _ => Err(format!("'{}' expects a column number, got '{}'", key, value)),
+    };
+
+    match key.trim().to_lowercase().as_str() {
+        "separator" => directives.separator = parse_separator(value).ok_or(format!("unknown separator '{}'", value))?,
+        "html" => directives.html = value.eq_ignore_ascii_case("true"),
+        "tags column" => directives.tags_column = Some(column()?),
+        "guid column" => directives.guid_column = Some(column()?),
+        "notetype column" | "deck column" => directives.skip_columns.push(column()?),
+        "deck" => directives.deck = Some(value.to_string()),
+        "tags" => directives.tags = value.split_whitespace().map(str::to_string).collect(),
+        // Only informational for this import
+        "notetype" | "columns" => {}
+        other => return Err(format!("unknown directive '{}', ignored", other)),
+    }
+    Ok(())
+}
+
+/// Reads Anki's separator names, or a single character given as is
+fn parse_separator(value: &str) -> Option<char> {
+    match value.to_lowercase().as_str() {
+        "tab" => Some('\t'),
+        "comma" => Some(','),
+        "semicolon" => Some(';'),
+        "pipe" => Some('|'),
+        "colon" => Some(':'),
+        "space" => Some(' '),
+        _ => {
+            let mut chars = value.chars();
+            chars.next().filter(|_| chars.next().is_none())
+        }
+    }
+}
+
+/// Turns an Anki deck name into a file name: `Languages::Spanish` becomes `Languages-Spanish`
+fn deck_file_name(deck: &str) -> String {
+    deck.replace("::", "-")
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Removes HTML tags, keeping line breaks for `<br>` and block elements, and decodes entities
+fn strip_html(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        plain.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            // A lone `<` is just text
+            plain.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim_start_matches('/').to_lowercase();
+        let name = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("");
+        if matches!(name, "br" | "div" | "p" | "li") && !plain.is_empty() && !plain.ends_with('\n') {
+            plain.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    plain.push_str(rest);
+    decode_entities(&plain).trim().to_string()
+}
+
+/// Replaces HTML character references like `&amp;` and `&#233;` with their characters
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .and_then(|end| entity_char(&rest[1..end]).map(|c| (c, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The character for an entity name (without `&` and `;`), if it is one
+fn entity_char(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => name.strip_prefix('#').and_then(|decimal| decimal.parse().ok()),
+            };
+            code.and_then(char::from_u32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_tags_are_stripped_and_breaks_kept() {
+        assert_eq!(strip_html("<b>bold</b> text"), "bold text");
+        assert_eq!(strip_html("line one<br>line two<br/>"), "line one\nline two");
+        assert_eq!(strip_html("<div>a</div><div>b</div>"), "a\nb");
+        assert_eq!(strip_html("<span class=\"x\">1 &lt; 2</span>"), "1 < 2");
+        // A lone `<` is text, not the start of a tag
+        assert_eq!(strip_html("1 < 2"), "1 < 2");
+    }
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(decode_entities("&amp;&lt;&gt;&quot;&apos;&nbsp;"), "&<>\"' ");
+        assert_eq!(decode_entities("caf&#233; &#x263A; &#X41;"), "café ☺ A");
+        // Anything that isn't a known entity is left alone
+        assert_eq!(decode_entities("AT&T &bogus; &#xD800; &#12"), "AT&T &bogus; &#xD800; &#12");
+    }
+
+    #[test]
+    fn separators_by_name_or_character() {
+        assert_eq!(parse_separator("tab"), Some('\t'));
+        assert_eq!(parse_separator("Comma"), Some(','));
+        assert_eq!(parse_separator("|"), Some('|'));
+        assert_eq!(parse_separator("ab"), None);
+        assert_eq!(parse_separator(""), None);
+    }
+
+    #[test]
+    fn directives_are_applied_or_reported() {
+        let mut directives = Directives::default();
+        for directive in ["separator:Comma", "html:true", "guid column:1", "tags column:4", "notetype column:2", "deck:A::B", "notetype:Basic"] {
+            assert!(apply_directive(&mut directives, directive).is_ok(), "{}", directive);
+        }
+        assert_eq!(directives.separator, ',');
+        assert!(directives.html);
+        assert_eq!((directives.guid_column, directives.tags_column), (Some(0), Some(3)));
+        assert_eq!(directives.skip_columns, [1]);
+        assert_eq!(directives.deck.as_deref(), Some("A::B"));
+
+        for directive in ["no colon", "tags column:0", "guid column:x", "separator:weird", "colour:red"] {
+            assert!(apply_directive(&mut directives, directive).is_err(), "{}", directive);
+        }
+    }
+
+    #[test]
+    fn deck_names_become_file_names() {
+        assert_eq!(deck_file_name("Languages::Spanish"), "Languages-Spanish");
+        assert_eq!(deck_file_name("Verbs (irregular)"), "Verbs__irregular_");
+    }
+
+    #[test]
+    fn imports_an_export_end_to_end() {
+        let folder = std::env::temp_dir().join(format!("flashcards-anki-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        let export = folder.join("export.txt");
+        let text = [
+            "#separator:tab",
+            "#html:true",
+            "#guid column:1",
+            "#notetype column:2",
+            "#deck column:3",
+            "#tags column:6",
+            "#deck:Languages::Spanish",
+            "#tags:imported",
+            "a1b2\tBasic\tSpanish\tperro\t<b>dog</b><br>hound\tanimal noun",
+            "c3d4\tCloze\tSpanish\t{{c1::gato}} means cat\tSee &quot;gatito&quot;\tanimal",
+            "e5f6\tBasic\tSpanish\tcaballo\t\t",
+        ]
+        .join("\n");
+        fs::write(&export, text).unwrap();
+        let export = export.to_string_lossy().to_string();
+        let decks = folder.join("decks").to_string_lossy().to_string();
+
+        let import = match import(&export, &decks) {
+            Ok(import) => import,
+            Err(e) => panic!("import failed: {}", e),
+        };
+        let written = fs::read_to_string(&import.path).unwrap();
+        let again = import_again(&export, &decks);
+        let _ = fs::remove_dir_all(&folder);
+
+        assert!(import.path.ends_with("Languages-Spanish.csv"), "{}", import.path);
+        assert_eq!(import.notes, 2);
+        // Notetype, deck, guid and tags columns leave the card fields; a cloze
+        // note's extra field becomes its notes
+        assert_eq!(
+            written,
+            "id,question,answer,tags,notes\n\
+             a1b2,perro,\"dog\nhound\",imported animal noun,\n\
+             c3d4,{{c1::gato}} means cat,,imported animal,\"See \"\"gatito\"\"\"\n"
+        );
+        let problems: Vec<String> = import.problems.iter().map(|problem| problem.reason.clone()).collect();
+        assert_eq!(problems, ["note has no back field, skipped"]);
+        assert_eq!(import.problems[0].position, Some((11, 1)));
+        // Importing twice never overwrites the first deck
+        assert!(again.contains("already exists"), "{}", again);
+    }
+
+    fn import_again(export: &str, folder: &str) -> String {
+        match import(export, folder) {
+            Ok(_) => "imported twice".to_string(),
+            Err(e) => e,
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::settings::DeckSettings;
+use crate::utils::levenshtein;
+
+/// How forgiving the typed-answer comparison is
+pub struct MatchOptions {
+    pub ignore_case: bool,
+    pub ignore_whitespace: bool,  // Drop all whitespace instead of just collapsing runs
+    pub ignore_punctuation: bool,
+    pub ignore_diacritics: bool,  // Treat "é" as "e"
+    pub max_typos: usize,         // Edit distance still accepted as correct
+}
+
+impl MatchOptions {
+    /// Reads the `typed_*` settings for a deck
+    pub fn from_settings(settings: &DeckSettings) -> Self {
+        MatchOptions {
+            ignore_case: settings.get_bool("typed_ignore_case", true),
+            ignore_whitespace: settings.get_bool("typed_ignore_whitespace", false),
+            ignore_punctuation: settings.get_bool("typed_ignore_punctuation", true),
+            ignore_diacritics: settings.get_bool("typed_ignore_diacritics", false),
+            max_typos: settings.get_usize("typed_max_typos", 1),
+        }
+    }
+
+    /// Folds a single character the way `normalize` does
+    fn fold(&self, c: char) -> char {
+        let c = if self.ignore_diacritics { strip_diacritic(c) } else { c };
+        if self.ignore_case {
+            c.to_lowercase().next().unwrap_or(c)
+        } else {
+            c
+        }
+    }
+
+    /// Applies the configured normalisation to some text
+    pub fn normalize(&self, text: &str) -> String {
+        let folded: String = text
+            .chars()
+            .filter(|c| !self.ignore_punctuation || c.is_alphanumeric() || c.is_whitespace())
+            .map(|c| self.fold(c))
+            .collect();
+
+        if self.ignore_whitespace {
+            folded.split_whitespace().collect()
+        } else {
+            folded.split_whitespace().collect::<Vec<_>>().join(" ")
+        }
+    }
+}
+
+/// Part of a character-level diff between a typed answer and the expected one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffSegment {
+    Same(String),    // Typed correctly
+    Extra(String),   // Typed but not in the answer
+    Missing(String), // In the answer but not typed
+}
+
+/// Result of checking a typed answer
+pub struct AnswerCheck {
+    pub typed: String,
+    pub correct: bool,
+    pub diff: Vec<DiffSegment>,
+}
+
+/// Compares a typed answer with the expected one.
+///
+/// Small typos are forgiven up to `max_typos`, but never more than one per
+/// four characters of the answer, so a short answer like "4" must be exact.
+pub fn check_answer(typed: &str, expected: &str, options: &MatchOptions) -> AnswerCheck {
+    let typed_normalized = options.normalize(typed);
+    let expected_normalized = options.normalize(expected);
+
+    let distance = levenshtein(&typed_normalized, &expected_normalized);
+    let allowed = options
+        .max_typos
+        .min(expected_normalized.chars().count() / 4);
+
+    AnswerCheck {
+        typed: typed.to_string(),
+        correct: !typed_normalized.is_empty() && distance <= allowed,
+        diff: diff_chars(typed.trim(), expected.trim(), options),
+    }
+}
+
+/// Character-level diff (longest common subsequence) of `typed` against `expected`
+pub fn diff_chars(typed: &str, expected: &str, options: &MatchOptions) -> Vec<DiffSegment> {
+    let a: Vec<char> = typed.chars().collect();
+    let b: Vec<char> = expected.chars().collect();
+    let same = |x: char, y: char| options.fold(x) == options.fold(y);
+
+    // lcs[i][j] = length of the LCS of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if same(a[i], b[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut segments: Vec<DiffSegment> = Vec::new();
+    let mut push = |segment: DiffSegment| {
+        // Merge with the previous segment when it is the same kind
+        match (segments.last_mut(), segment) {
+            (Some(DiffSegment::Same(text)), DiffSegment::Same(c)) => text.push_str(&c),
+            (Some(DiffSegment::Extra(text)), DiffSegment::Extra(c)) => text.push_str(&c),
+            (Some(DiffSegment::Missing(text)), DiffSegment::Missing(c)) => text.push_str(&c),
+            (_, segment) => segments.push(segment),
+        }
+    };
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && same(a[i], b[j]) {
+            // Show the expected spelling for matched characters
+            push(DiffSegment::Same(b[j].to_string()));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            push(DiffSegment::Missing(b[j].to_string()));
+            j += 1;
+        } else {
+            push(DiffSegment::Extra(a[i].to_string()));
+            i += 1;
+        }
+    }
+
+    segments
+}
+
+/// Maps common accented Latin letters to their base letter
+fn strip_diacritic(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => 'A',
+        'ç' | 'ć' | 'č' => 'c',
+        'Ç' | 'Ć' | 'Č' => 'C',
+        'ď' => 'd',
+        'Ď' => 'D',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => 'E',
+        'ğ' => 'g',
+        'Ğ' => 'G',
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => 'i',
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' | 'Į' | 'İ' => 'I',
+        'ł' | 'ľ' => 'l',
+        'Ł' | 'Ľ' => 'L',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'Ñ' | 'Ń' | 'Ň' => 'N',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ő' => 'O',
+        'ř' => 'r',
+        'Ř' => 'R',
+        'ś' | 'š' | 'ş' => 's',
+        'Ś' | 'Š' | 'Ş' => 'S',
+        'ť' | 'ţ' => 't',
+        'Ť' | 'Ţ' => 'T',
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => 'u',
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' | 'Ű' | 'Ų' => 'U',
+        'ý' | 'ÿ' => 'y',
+        'Ý' | 'Ÿ' => 'Y',
+        'ź' | 'ż' | 'ž' => 'z',
+        'Ź' | 'Ż' | 'Ž' => 'Z',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DiffSegment::*;
+
+    fn options(max_typos: usize, ignore_diacritics: bool) -> MatchOptions {
+        MatchOptions {
+            ignore_case: true,
+            ignore_whitespace: false,
+            ignore_punctuation: true,
+            ignore_diacritics,
+            max_typos,
+        }
+    }
+
+    #[test]
+    fn typos_are_capped_by_answer_length() {
+        // (typed, expected, max_typos, correct)
+        let cases = [
+            ("4", "4", 1, true),
+            ("4", "5", 1, false),
+            ("pariss", "Paris", 1, true),
+            ("parsi", "Paris", 1, false),
+            ("paris!", "Paris", 0, true),
+            ("  new   york ", "New York", 0, true),
+            ("Misisippi", "Mississippi", 5, true),
+            ("Misisipi", "Mississippi", 5, false),
+            ("", "Paris", 5, false),
+        ];
+        for (typed, expected, max_typos, correct) in cases {
+            let check = check_answer(typed, expected, &options(max_typos, false));
+            assert_eq!(check.correct, correct, "{:?} vs {:?}", typed, expected);
+        }
+    }
+
+    #[test]
+    fn diacritics_only_matter_when_not_ignored() {
+        let strict = check_answer("creme brulee", "crème brûlée", &options(0, false));
+        let lenient = check_answer("creme brulee", "crème brûlée", &options(0, true));
+        assert!(!strict.correct);
+        assert!(lenient.correct);
+    }
+
+    #[test]
+    fn diff_marks_extra_and_missing_characters() {
+        // (typed, expected, ignore_diacritics, diff)
+        let cases = [
+            ("pariss", "Paris", false, vec![Same("Paris".into()), Extra("s".into())]),
+            ("Pars", "Paris", false, vec![Same("Par".into()), Missing("i".into()), Same("s".into())]),
+            ("4", "5", false, vec![Missing("5".into()), Extra("4".into())]),
+            ("creme", "crème", true, vec![Same("crème".into())]),
+            (
+                "creme",
+                "crème",
+                false,
+                vec![Same("cr".into()), Missing("è".into()), Extra("e".into()), Same("me".into())],
+            ),
+        ];
+        for (typed, expected, ignore_diacritics, diff) in cases {
+            assert_eq!(diff_chars(typed, expected, &options(1, ignore_diacritics)), diff, "{:?} vs {:?}", typed, expected);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+/// One card generated from a cloze deletion, e.g. `The {{c1::let}} keyword`
+pub struct ClozeCard {
+    pub index: u32,                 // The N in `cN`
+    pub front: String,              // Text with this deletion replaced by `[...]`
+    pub back: String,               // Full text with every deletion filled in
+    pub spans: Vec<(usize, usize)>, // Byte ranges in `back` that were hidden on the front
+    pub hidden: String,             // The hidden text, used as the expected answer
+}
+
+/// A piece of cloze text: plain text or a deletion
+enum Part {
+    Text(String),
+    Deletion {
+        index: u32,
+        text: String,
+        hint: Option<String>,
+    },
+}
+
+/// Expands cloze text into one card per deletion index, in index order.
+/// Text without any deletions gives no cards.
+///
+/// Deletions sharing an index are hidden together. An optional hint
+/// (`{{c1::let::keyword}}`) is shown in place of `[...]`.
+pub fn expand(text: &str) -> Vec<ClozeCard> {
+    let parts = parse(text);
+
+    let mut indices: Vec<u32> = parts
+        .iter()
+        .filter_map(|part| match part {
+            Part::Deletion { index, .. } => Some(*index),
+            Part::Text(_) => None,
+        })
+        .collect();
+    indices.sort();
+    indices.dedup();
+
+    indices
+        .into_iter()
+        .map(|current| {
+            let mut front = String::new();
+            let mut back = String::new();
+            let mut spans = Vec::new();
+            let mut hidden = Vec::new();
+
+            for part in &parts {
+                match part {
+                    Part::Text(text) => {
+                        front.push_str(text);
+                        back.push_str(text);
+                    }
+                    Part::Deletion { index, text, hint } if *index == current => {
+                        match hint {
+                            Some(hint) => front.push_str(&format!("[{}]", hint)),
+                            None => front.push_str("[...]"),
+                        }
+                        spans.push((back.len(), back.len() + text.len()));
+                        back.push_str(text);
+                        hidden.push(text.as_str());
+                    }
+                    Part::Deletion { text, .. } => {
+                        front.push_str(text);
+                        back.push_str(text);
+                    }
+                }
+            }
+
+            ClozeCard {
+                index: current,
+                front,
+                back,
+                spans,
+                hidden: hidden.join(", "),
+            }
+        })
+        .collect()
+}
+
+/// Splits text into plain parts and `{{cN::text}}` / `{{cN::text::hint}}` deletions.
+/// Anything that does not parse as a deletion is kept as plain text.
+fn parse(text: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let after_open = &rest[start + 2..];
+        let deletion = after_open
+            .find("}}")
+            .and_then(|end| parse_deletion(&after_open[..end]).map(|d| (d, end)));
+
+        match deletion {
+            Some((part, end)) => {
+                plain.push_str(&rest[..start]);
+                if !plain.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut plain)));
+                }
+                parts.push(part);
+                rest = &after_open[end + 2..];
+            }
+            None => {
+                // Not a deletion: keep the braces as text and continue after them
+                plain.push_str(&rest[..start + 2]);
+                rest = after_open;
+            }
+        }
+    }
+
+    plain.push_str(rest);
+    if !plain.is_empty() {
+        parts.push(Part::Text(plain));
+    }
+
+    parts
+}
+
+/// Parses the inside of `{{...}}`, e.g. `c1::let` or `c2::mut::keyword`
+fn parse_deletion(inner: &str) -> Option<Part> {
+    let mut pieces = inner.splitn(3, "::");
+    let index = pieces.next()?.trim().strip_prefix(['c', 'C'])?.parse().ok()?;
+    let text = pieces.next()?.to_string();
+    let hint = pieces.next().map(|hint| hint.to_string());
+
+    if text.is_empty() {
+        return None;
+    }
+
+    Some(Part::Deletion { index, text, hint })
+}
@@ -0,0 +1,93 @@
+/// A column a deck file can have, named in its header row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    Question,
+    Answer,
+    Hint,
+    Tags,
+    Notes,
+    Id,
+}
+
+impl Column {
+    /// Reads a header name, ignoring case. `front` and `back` are accepted for
+    /// question and answer, as exported by other flashcard apps.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "question" | "front" => Some(Column::Question),
+            "answer" | "back" => Some(Column::Answer),
+            "hint" => Some(Column::Hint),
+            "tags" | "tag" => Some(Column::Tags),
+            "notes" | "note" => Some(Column::Notes),
+            "id" => Some(Column::Id),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Question => "question",
+            Column::Answer => "answer",
+            Column::Hint => "hint",
+            Column::Tags => "tags",
+            Column::Notes => "notes",
+            Column::Id => "id",
+        }
+    }
+}
+
+/// Which field of a row holds each column
+pub struct ColumnMap {
+    indices: [Option<usize>; 6], // Field index for each column, indexed by `Column as usize`
+}
+
+impl ColumnMap {
+    /// Layout of a deck without a header: question, then answer. Cards are
+    /// identified by their question; an ID column needs a header naming it.
+    pub fn positional() -> Self {
+        ColumnMap {
+            indices: [Some(0), Some(1), None, None, None, None],
+        }
+    }
+
+    /// Builds the map from a header row, if the row is one.
+    ///
+    /// A row counts as a header when it names both a question and an answer
+    /// column. Also returns a note for every name that was not understood or
+    /// repeated, with its field index; those fields are ignored.
+    pub fn from_header(fields: &[String]) -> Option<(Self, Vec<(usize, String)>)> {
+        let mut indices = [None; 6];
+        let mut notes = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            if field.is_empty() {
+                continue;
+            }
+            match Column::parse(field) {
+                Some(column) => {
+                    let slot = &mut indices[column as usize];
+                    if slot.is_some() {
+                        notes.push((i, format!("column '{}' appears twice, ignoring this one", column.name())));
+                    } else {
+                        *slot = Some(i);
+                    }
+                }
+                None => notes.push((i, format!("unknown column '{}' ignored", field))),
+            }
+        }
+
+        let map = ColumnMap { indices };
+        (map.index(Column::Question).is_some() && map.index(Column::Answer).is_some()).then_some((map, notes))
+    }
+
+    /// Field index of a column, if the deck has it
+    pub fn index(&self, column: Column) -> Option<usize> {
+        self.indices[column as usize]
+    }
+
+    /// Value of a column in a row; empty if the deck or the row doesn't have it
+    pub fn get<'a>(&self, fields: &'a [String], column: Column) -> &'a str {
+        self.index(column)
+            .and_then(|i| fields.get(i))
+            .map_or("", |field| field.as_str())
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+
+/// One row of a CSV file
+pub struct Record {
+    pub fields: Vec<String>,            // Trimmed field values, with quotes removed
+    pub positions: Vec<(usize, usize)>, // Line and column where each field starts, counting from 1
+}
+
+/// A problem that stops the rest of the file from being read
+#[derive(Debug)]
+pub struct CsvError {
+    pub line: usize,
+    pub column: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.reason)
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+/// Separators a deck's fields can be split on
+pub const DELIMITERS: [char; 4] = [',', '\t', ';', '|'];
+
+/// How many lines are looked at to guess the delimiter
+const SNIFF_LINES: usize = 5;
+
+/// Reads a `delimiter` setting: a name like `tab` or the character itself
+pub fn parse_delimiter(name: &str) -> Option<char> {
+    match name.to_lowercase().as_str() {
+        "comma" | "," => Some(','),
+        "tab" | "\\t" => Some('\t'),
+        "semicolon" | ";" => Some(';'),
+        "pipe" | "|" => Some('|'),
+        _ => None,
+    }
+}
+
+/// Guesses the delimiter from the first lines of a file. The best candidate
+/// appears the same number of times on every line, so every row splits into
+/// the same number of fields. `preferred` wins ties, even when another
+/// candidate appears more often: a comma-separated answer in a tab-separated
+/// list should not decide the split.
+pub fn sniff_delimiter(text: &str, preferred: char) -> char {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).take(SNIFF_LINES).collect();
+    let mut candidates = vec![preferred];
+    candidates.extend(DELIMITERS.iter().copied().filter(|&c| c != preferred));
+
+    let mut best = (preferred, (false, false));
+    for delimiter in candidates {
+        let counts: Vec<usize> = lines.iter().map(|line| count_unquoted(line, delimiter)).collect();
+        let present = !counts.is_empty() && counts.iter().all(|&count| count > 0);
+        let consistent = present && counts.iter().all(|&count| count == counts[0]);
+        let score = (consistent, present);
+        if score > best.1 {
+            best = (delimiter, score);
+        }
+    }
+    best.0
+}
+
+/// Counts a character on one line, leaving out any inside quoted fields. As
+/// in `read_records`, a quote only opens a quoted field at the start of a field.
+fn count_unquoted(line: &str, delimiter: char) -> usize {
+    let mut in_quotes = false;
+    let mut field_start = true; // Only whitespace so far in this field
+    let mut count = 0;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            // A doubled quote is an escaped quote and keeps the field open
+            if c == '"' && chars.next_if_eq(&'"').is_none() {
+                in_quotes = false;
+            }
+        } else if c == '"' && field_start {
+            in_quotes = true;
+            field_start = false;
+        } else if c == delimiter {
+            count += 1;
+            field_start = true;
+        } else if !c.is_whitespace() {
+            field_start = false;
+        }
+    }
+    count
+}
+
+/// Splits CSV text into records, following RFC 4180, with fields separated
+/// by `delimiter` (a comma for standard CSV).
+///
+/// Quoted fields may contain the delimiter, doubled quotes and line breaks. Records
+/// may end in `\n` or `\r\n`, and a leading byte order mark is ignored. A
+/// quote only opens a quoted field at the start of a field; anywhere else it
+/// is kept as text. Blank lines are skipped. A quote left open at the end of
+/// the file is an error, reported at the line and column where it opened.
+pub fn read_records(text: &str, delimiter: char) -> Result<Vec<Record>, CsvError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut positions = vec![(1, 1)];
+    let mut field = String::new();
+    let mut quoted = false;     // Field started with a quote
+    let mut in_quotes = false;  // Inside that quote right now
+    let mut opened_at = (1, 1); // Where the open quote is, for errors
+    let mut line = 1;
+    let mut column = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        column += 1;
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    // Escaped quote (two quotes in a row)
+                    field.push('"');
+                    chars.next();
+                    column += 1;
+                }
+                '"' => in_quotes = false,
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    field.push('\n');
+                    line += 1;
+                    column = 0;
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if !quoted && field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+                in_quotes = true;
+                opened_at = (line, column);
+            }
+            c if c == delimiter => {
+                fields.push(finish_field(&mut field));
+                positions.push((line, column + 1));
+                quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(finish_field(&mut field));
+                quoted = false;
+                push_record(&mut records, &mut fields, &mut positions);
+                line += 1;
+                column = 0;
+                positions.push((line, 1));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(CsvError {
+            line: opened_at.0,
+            column: opened_at.1,
+            reason: "quoted field is never closed".to_string(),
+        });
+    }
+    if !field.is_empty() || quoted || !fields.is_empty() {
+        fields.push(finish_field(&mut field));
+        push_record(&mut records, &mut fields, &mut positions);
+    }
+
+    Ok(records)
+}
+
+/// Writes one record as a line of CSV text, quoting fields that need it
+pub fn format_record(fields: &[&str], delimiter: char) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([delimiter, '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    fields.join(&delimiter.to_string())
+}
+
+/// Takes the finished field's text, trimmed like the rest of the deck
+fn finish_field(field: &mut String) -> String {
+    std::mem::take(field).trim().to_string()
+}
+
+/// Stores a record unless the line was blank
+fn push_record(records: &mut Vec<Record>, fields: &mut Vec<String>, positions: &mut Vec<(usize, usize)>) {
+    let fields = std::mem::take(fields);
+    let positions = std::mem::take(positions);
+    if fields.len() == 1 && fields[0].is_empty() {
+        return;
+    }
+    records.push(Record { fields, positions });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(text: &str) -> Vec<Vec<String>> {
+        match read_records(text, ',') {
+            Ok(records) => records.into_iter().map(|record| record.fields).collect(),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn doubled_quotes_are_one_quote() {
+        assert_eq!(fields("\"Who wrote \"\"Hamlet\"\"?\",Shakespeare\n"), [["Who wrote \"Hamlet\"?", "Shakespeare"]]);
+    }
+
+    #[test]
+    fn quoted_fields_keep_delimiters_and_newlines() {
+        let records = fields("States of matter,\"Solid,\nLiquid\nGas\"\nnext,card\n");
+        assert_eq!(records, [["States of matter", "Solid,\nLiquid\nGas"], ["next", "card"]]);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let records = fields("a,b\r\n\"c\r\nd\",e\r\n");
+        assert_eq!(records, [["a", "b"], ["c\nd", "e"]]);
+    }
+
+    #[test]
+    fn byte_order_mark_is_skipped() {
+        assert_eq!(fields("\u{feff}question,answer"), [["question", "answer"]]);
+    }
+
+    #[test]
+    fn stray_quote_mid_field_is_text() {
+        assert_eq!(fields("it's 5\" long,yes\n"), [["it's 5\" long", "yes"]]);
+    }
+
+    #[test]
+    fn blank_lines_are_skipped_and_positions_kept() {
+        let records = match read_records("a,b\n\n  c , d\n", ',') {
+            Ok(records) => records,
+            Err(e) => panic!("unexpected error: {}", e),
+        };
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].fields, ["c", "d"]);
+        assert_eq!(records[1].positions, [(3, 1), (3, 6)]);
+    }
+
+    #[test]
+    fn unterminated_quote_reports_where_it_opened() {
+        let error = match read_records("a,b\nc,\"d\ne,f\n", ',') {
+            Ok(_) => panic!("expected an error"),
+            Err(error) => error,
+        };
+        assert_eq!((error.line, error.column), (2, 3));
+    }
+
+    #[test]
+    fn sniff_picks_the_delimiter_used_on_every_line() {
+        assert_eq!(sniff_delimiter("a;b;c\nd;e;f\n", ','), ';');
+        assert_eq!(sniff_delimiter("a|b\nc|d\n", '\t'), '|');
+        assert_eq!(sniff_delimiter("q, a\n\"x, y\", z\n", '\t'), ',');
+    }
+
+    #[test]
+    fn sniff_prefers_tab_on_ties() {
+        // One tab and one comma per line: both split every row the same way
+        let list = "perro\tdog, hound\ngato\tcat, puss\n";
+        assert_eq!(sniff_delimiter(list, '\t'), '\t');
+        // More commas than tabs on every line still leaves the tab preferred
+        let list = "perro\tdog, hound, pup\ngato\tcat, puss, kitty\n";
+        assert_eq!(sniff_delimiter(list, '\t'), '\t');
+    }
+
+    #[test]
+    fn sniff_only_opens_quotes_at_the_start_of_a_field() {
+        // Inch marks mid-field are text, as the reader sees them
+        assert_eq!(sniff_delimiter("5\" screen;big\n7\" tablet;small\n", '\t'), ';');
+        // Doubled quotes inside a quoted field don't close it
+        assert_eq!(sniff_delimiter("\"say \"\"hi; there\"\"\";greeting\nbye;farewell\n", '\t'), ';');
+    }
+
+    #[test]
+    fn sniff_prefers_a_consistent_count() {
+        // Tabs split every line once; commas only appear in some answers
+        let list = "perro\tdog, hound\ngato\tcat\nrojo\tred, crimson, scarlet\n";
+        assert_eq!(sniff_delimiter(list, ','), '\t');
+    }
+
+    #[test]
+    fn sniff_falls_back_to_preferred() {
+        assert_eq!(sniff_delimiter("", '\t'), '\t');
+        assert_eq!(sniff_delimiter("one field\nanother\n", ','), ',');
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+
+/// A problem found while loading a deck, pointing at where it is in the file
+pub struct Diagnostic {
+    pub file: String,
+    pub position: Option<(usize, usize)>, // Line and column, or None for the whole file
+    pub reason: String,
+}
+
+impl Diagnostic {
+    pub fn at(file: &str, line: usize, column: usize, reason: impl Into<String>) -> Self {
+        Diagnostic {
+            file: file.to_string(),
+            position: Some((line, column)),
+            reason: reason.into(),
+        }
+    }
+
+    /// A problem with the file as a whole, such as it not being readable
+    pub fn file(file: &str, reason: impl Into<String>) -> Self {
+        Diagnostic {
+            file: file.to_string(),
+            position: None,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// Formats as `file:line:column: reason`, like compiler messages
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "{}:{}:{}: {}", self.file, line, column, self.reason),
+            None => write!(f, "{}: {}", self.file, self.reason),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use crate::settings::DeckSettings;
+
+/// How an exam is set up for a deck
+pub struct ExamSettings {
+    pub size: usize,                  // Number of cards drawn for each exam
+    pub time_limit: Option<Duration>, // None when the exam is untimed
+}
+
+impl ExamSettings {
+    /// Reads `exam_size` and `exam_time_limit` (in minutes, 0 for none) for a deck
+    pub fn from_settings(settings: &DeckSettings) -> Self {
+        let minutes = settings.get_f64("exam_time_limit", 0.0);
+        ExamSettings {
+            size: settings.get_usize("exam_size", 20).max(1),
+            time_limit: (minutes > 0.0).then(|| Duration::from_secs_f64(minutes * 60.0)),
+        }
+    }
+}
+
+/// A running or finished exam: which cards were answered right or wrong and how long it took
+pub struct Exam {
+    pub cards: Vec<usize>,            // Indices of the cards drawn for the exam
+    pub results: Vec<(usize, bool)>,  // Card index and whether it was answered correctly
+    started: Instant,
+    time_limit: Option<Duration>,
+    finished: Option<Instant>,
+}
+
+impl Exam {
+    pub fn new(cards: Vec<usize>, time_limit: Option<Duration>) -> Self {
+        Exam {
+            cards,
+            results: Vec::new(),
+            started: Instant::now(),
+            time_limit,
+            finished: None,
+        }
+    }
+
+    /// Records the answer to one card; the exam ends once every card is answered
+    pub fn record(&mut self, card: usize, correct: bool) {
+        if self.is_finished() {
+            return;
+        }
+        self.results.push((card, correct));
+        if self.results.len() >= self.cards.len() {
+            self.finished = Some(Instant::now());
+        }
+    }
+
+    /// Ends the exam if its time limit has passed. Unanswered cards count as missed.
+    /// Returns true if this call ended it.
+    pub fn check_time(&mut self) -> bool {
+        let timed_out = match (self.finished, self.remaining()) {
+            (None, Some(remaining)) => remaining.is_zero(),
+            _ => false,
+        };
+        if timed_out {
+            let unanswered: Vec<usize> = self
+                .cards
+                .iter()
+                .copied()
+                .filter(|card| !self.results.iter().any(|(answered, _)| answered == card))
+                .collect();
+            self.results.extend(unanswered.into_iter().map(|card| (card, false)));
+            self.finished = Some(Instant::now());
+        }
+        timed_out
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    /// Time spent so far, or in total once finished
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now) - self.started
+    }
+
+    /// Time left before the limit, if the exam is timed
+    pub fn remaining(&self) -> Option<Duration> {
+        self.time_limit.map(|limit| limit.saturating_sub(self.elapsed()))
+    }
+
+    pub fn correct_count(&self) -> usize {
+        self.results.iter().filter(|(_, correct)| *correct).count()
+    }
+
+    /// Score as a percentage of all the cards in the exam
+    pub fn percent(&self) -> f64 {
+        if self.cards.is_empty() {
+            return 0.0;
+        }
+        self.correct_count() as f64 * 100.0 / self.cards.len() as f64
+    }
+
+    /// Cards answered wrongly or not at all, in the order they were asked
+    pub fn missed(&self) -> Vec<usize> {
+        self.results
+            .iter()
+            .filter(|(_, correct)| !correct)
+            .map(|(card, _)| *card)
+            .collect()
+    }
+}
+
+/// Formats a duration as minutes and seconds, e.g. `3:07`
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
@@ -1,56 +1,869 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
 use raylib::prelude::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::collections::HashSet;
+use std::fs;
+use std::time::{Duration, Instant};
 
+use crate::answer_check::{check_answer, AnswerCheck, DiffSegment, MatchOptions};
+use crate::columns::{Column, ColumnMap};
+use crate::diagnostics::Diagnostic;
+use crate::exam::{format_duration, Exam, ExamSettings};
+use crate::matching::MatchingRound;
+use crate::options::{Direction, Options, SessionMode, StudyMode};
+use crate::progress::{content_id, ProgressStore};
+use crate::quiz::MultipleChoice;
+use crate::scheduler::{Grade, LearningSteps, ReviewState, Scheduler, Stage};
+use crate::settings::DeckSettings;
+use crate::stats::SessionStats;
 use crate::utils::DeckManager;
 
+mod anki;
+mod answer_check;
+mod cloze;
+mod columns;
+mod csv;
+mod diagnostics;
+mod exam;
+mod matching;
+mod options;
+mod progress;
+mod quiz;
+mod scheduler;
+mod settings;
+mod stats;
 mod utils;
 
+/// Keys used to grade a card once its answer is showing
+const GRADE_KEYS: [(KeyboardKey, Grade); 4] = [
+    (KeyboardKey::KEY_ONE, Grade::Again),
+    (KeyboardKey::KEY_TWO, Grade::Hard),
+    (KeyboardKey::KEY_THREE, Grade::Good),
+    (KeyboardKey::KEY_FOUR, Grade::Easy),
+];
+
+/// Folder the decks are read from, and Anki exports are imported into
+const DECK_FOLDER: &str = "./flashcard_decks";
+
+/// How many cards later a missed card comes back when cramming
+const CRAM_REQUEUE_GAP: usize = 3;
+
 #[derive(Clone)]
 struct Flashcard {
+    id: String,      // Stable identity used to key saved progress
+    position: usize, // Row order in the deck file; reverse cards follow all forward cards
+    deck: usize,     // Index into the session's `decks`
     question: String,
     answer: String,
+    reversed: bool,                // Answer-first sibling of another card
+    hidden: Option<String>,        // Text hidden by a cloze deletion, if this is a cloze card
+    highlights: Vec<(usize, usize)>, // Byte ranges of the answer to highlight (cloze fill-ins)
+    hint: Option<String>,            // Clue that can be revealed before flipping
+    tags: Vec<String>,
+    notes: Option<String>,           // Extra detail shown with the answer
+    schedule: ReviewState,
+}
+
+impl Flashcard {
+    /// Text shown before the card is flipped
+    fn front(&self) -> &str {
+        if self.reversed {
+            &self.answer
+        } else {
+            &self.question
+        }
+    }
+
+    /// Text shown once the card is flipped
+    fn back(&self) -> &str {
+        if self.reversed {
+            &self.question
+        } else {
+            &self.answer
+        }
+    }
+
+    /// What the user is expected to recall: the hidden text for cloze cards,
+    /// otherwise the back of the card
+    fn expected_answer(&self) -> &str {
+        self.hidden.as_deref().unwrap_or(self.back())
+    }
+
+    /// Clue for the question; answer-first cards have none, since it hints at the question
+    fn hint(&self) -> Option<&str> {
+        self.hint.as_deref().filter(|_| !self.reversed)
+    }
+
+    /// Creates the answer-first sibling of this card, with its own progress
+    fn reversed(&self) -> Flashcard {
+        Flashcard {
+            id: format!("{}:reverse", self.id),
+            reversed: true,
+            schedule: ReviewState::default(),
+            ..self.clone()
+        }
+    }
+}
+
+/// What the window is showing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Screen {
+    Study,   // Cards, one at a time
+    Summary, // End-of-session summary with what to do next
+}
+
+/// One deck taking part in a session, with its own schedule and saved progress
+struct StudyDeck {
+    name: String,
+    scheduler: Box<dyn Scheduler>,
+    progress: ProgressStore,
+    match_options: MatchOptions, // How strictly typed answers are compared
+    learning_steps: LearningSteps,
+    limits: DailyLimits,
+}
+
+impl StudyDeck {
+    /// Opens the deck at `path`: its settings and saved progress
+    fn load(name: &str, path: &str) -> Self {
+        let settings = DeckSettings::load(path);
+        StudyDeck {
+            name: name.to_string(),
+            scheduler: scheduler::from_settings(&settings),
+            progress: ProgressStore::load(path),
+            match_options: MatchOptions::from_settings(&settings),
+            learning_steps: LearningSteps::from_settings(&settings),
+            limits: DailyLimits {
+                new_cards: settings.get_usize("new_cards_per_day", 20),
+                reviews: settings.get_usize("reviews_per_day", usize::MAX),
+            },
+        }
+    }
+}
+
+/// Caps on how many cards are studied per day, for one deck or a whole session
+struct DailyLimits {
+    new_cards: usize, // New cards introduced
+    reviews: usize,   // Graduated cards reviewed
+}
+
+/// Takes one card from the caps that apply to it, or returns false if any is used up
+fn take_from_caps(caps: &mut [&mut usize]) -> bool {
+    if caps.iter().any(|left| **left == 0) {
+        return false;
+    }
+    for left in caps.iter_mut() {
+        **left -= 1;
+    }
+    true
 }
 
 struct FlashcardGame {
+    screen: Screen,
+    stats: SessionStats, // Answers given this session, for the summary
+    decks: Vec<StudyDeck>,
     cards: Vec<Flashcard>,
-    current_index: usize,
+    due_cards: Vec<usize>, // Queue of indices into `cards` still to be answered this session
+    current_index: usize,  // Position within `due_cards`
+    learning_wait: Vec<usize>, // Review: learning cards not due yet, soonest first
     is_flipped: bool,
+    shuffle_seed: Option<u64>, // Seed of the current shuffle, None when in file order
+    direction: Direction,      // Which of the cards (forward and/or reversed) are studied
+    mode: StudyMode,
+    session: SessionMode,
+    hits: usize,           // Cards answered in time in a drill session
+    misses: usize,         // Cards missed so far in a cram or drill session, counting repeats
+    drill_limit: Duration, // Time allowed per card in a drill session
+    card_shown: Instant,   // When the current card was put on screen
+    timed_out: bool,       // The drill countdown ran out on the current card
+    drill_timeouts: Vec<usize>, // Drill: cards that ran out of time, already counted as missed
+    total_limits: DailyLimits, // Caps across every deck in the session
+    rollover_hour: u64,        // Local hour when a new study day starts
+    exam_settings: ExamSettings,
+    exam: Option<Exam>,    // The exam being taken, or its results once finished
+    matching_pairs: usize, // Pairs laid out in each round of the matching game
+    matching: Option<MatchingRound>,
+    slide_flip_delay: Duration, // Slideshow: time before the answer is shown
+    slide_next_delay: Duration, // Slideshow: time the answer stays up before the next card
+    slide_loop: bool,           // Slideshow: start over after the last card instead of stopping
+    paused_at: Option<Instant>, // When the slideshow was paused, None while it is playing
+    typed_answer: String,              // What the user has typed for the current card
+    answer_check: Option<AnswerCheck>, // Result of checking the typed answer
+    choice: Option<MultipleChoice>,    // Options for the current card in multiple-choice mode
+    quiz_correct: usize,               // Multiple-choice questions answered correctly this session
+    quiz_answered: usize,              // Multiple-choice questions answered this session
+    hint_shown: bool,                  // Whether the hint for the current card was revealed
+    problems: Vec<Diagnostic>,         // Rows and files that could not be loaded
+    show_problems: bool,               // Whether the deck problems panel is open
+    problems_opened_at: Option<Instant>, // When the problems panel was opened, to hold the card timers
 }
 
 impl FlashcardGame {
-    fn new(cards: Vec<Flashcard>) -> Self {
-        FlashcardGame {
+    /// Starts a session over one or more decks and their cards. Session-wide
+    /// options (exam, drill, matching, slideshow) come from `settings`.
+    fn new(sources: Vec<(StudyDeck, Vec<Flashcard>)>, settings: &DeckSettings) -> Self {
+        let deck_count = sources.len();
+        // Reverse cards come after every forward card, so in both directions
+        // a card's answer isn't asked for right after it was shown
+        let reverse_offset = sources.iter().map(|(_, cards)| cards.len()).max().unwrap_or(0) * deck_count;
+        let mut decks = Vec::new();
+        let mut cards = Vec::new();
+
+        for (d, (mut deck, mut deck_cards)) in sources.into_iter().enumerate() {
+            for card in deck_cards.iter_mut() {
+                card.deck = d;
+                // Interleave the decks: the first card of each, then the second, ...
+                card.position = card.position * deck_count + d;
+            }
+
+            // Every card also gets an answer-first sibling for reverse study,
+            // except cloze cards, which only make sense one way
+            let reversed: Vec<Flashcard> = deck_cards
+                .iter()
+                .filter(|card| card.hidden.is_none())
+                .map(|card| Flashcard {
+                    position: card.position + reverse_offset,
+                    ..card.reversed()
+                })
+                .collect();
+            deck_cards.extend(reversed);
+
+            reconcile_progress(&mut deck.progress, &deck_cards);
+
+            // Pick up where the last session left off
+            for card in deck_cards.iter_mut() {
+                if let Some(state) = deck.progress.get(&card.id) {
+                    card.schedule = state.clone();
+                }
+            }
+
+            decks.push(deck);
+            cards.extend(deck_cards);
+        }
+        cards.sort_by_key(|card| card.position);
+
+        let mut game = FlashcardGame {
+            screen: Screen::Study,
+            stats: SessionStats::start(),
+            decks,
             cards,
+            due_cards: Vec::new(),
+            learning_wait: Vec::new(),
             current_index: 0,
             is_flipped: false,
+            shuffle_seed: None,
+            direction: Direction::Forward,
+            mode: StudyMode::Flip,
+            session: SessionMode::Review,
+            hits: 0,
+            misses: 0,
+            drill_limit: Duration::from_secs_f64(settings.get_f64("drill_seconds", 10.0).max(1.0)),
+            card_shown: Instant::now(),
+            timed_out: false,
+            drill_timeouts: Vec::new(),
+            total_limits: DailyLimits {
+                new_cards: settings.get_usize("total_new_cards_per_day", usize::MAX),
+                reviews: settings.get_usize("total_reviews_per_day", usize::MAX),
+            },
+            rollover_hour: settings.get_usize("day_rollover_hour", 0) as u64,
+            exam_settings: ExamSettings::from_settings(settings),
+            exam: None,
+            matching_pairs: settings.get_usize("matching_pairs", 6).clamp(2, 8),
+            matching: None,
+            slide_flip_delay: Duration::from_secs_f64(settings.get_f64("slideshow_flip_seconds", 5.0).max(0.5)),
+            slide_next_delay: Duration::from_secs_f64(settings.get_f64("slideshow_next_seconds", 3.0).max(0.5)),
+            slide_loop: settings.get_bool("slideshow_loop", true),
+            paused_at: None,
+            typed_answer: String::new(),
+            answer_check: None,
+            choice: None,
+            quiz_correct: 0,
+            quiz_answered: 0,
+            hint_shown: false,
+            problems: Vec::new(),
+            show_problems: false,
+            problems_opened_at: None,
+        };
+        game.rebuild_due_cards();
+        game
+    }
+
+    /// Builds the session's queue: today's reviews for a review session.
+    /// Cram and exam sessions take every card, due or not; an exam then draws
+    /// `exam_size` of them at random.
+    fn rebuild_due_cards(&mut self) {
+        let mut due_cards = if self.session == SessionMode::Review {
+            self.build_daily_queue(scheduler::now())
+        } else {
+            let cards = &self.cards;
+            let mut studied: Vec<usize> = (0..cards.len()).filter(|&i| self.is_studied(&cards[i])).collect();
+            studied.sort_by_key(|&i| self.deck_of(&cards[i]).scheduler.queue_order(&cards[i].schedule));
+            studied
+        };
+        let cards = &self.cards;
+
+        self.exam = None;
+        if self.session == SessionMode::Exam {
+            // Reuse the shuffle seed so a seeded exam draws the same cards
+            let seed = self.shuffle_seed.unwrap_or_else(rand::random);
+            due_cards.shuffle(&mut StdRng::seed_from_u64(seed));
+            due_cards.truncate(self.exam_settings.size);
+            self.exam = Some(Exam::new(due_cards.clone(), self.exam_settings.time_limit));
+        }
+
+        self.matching = None;
+        if self.session == SessionMode::Match {
+            let mut rng = StdRng::seed_from_u64(self.shuffle_seed.unwrap_or_else(rand::random));
+            due_cards.shuffle(&mut rng);
+            due_cards.truncate(self.matching_pairs);
+            let pairs: Vec<(String, String)> = due_cards
+                .iter()
+                .map(|&i| (cards[i].front().to_string(), cards[i].expected_answer().to_string()))
+                .collect();
+            self.matching = Some(MatchingRound::new(&pairs, &mut rng));
+
+            // The round holds its own cards; there is no single current card
+            due_cards.clear();
+        }
+
+        self.due_cards = due_cards;
+        self.hold_back_learning(scheduler::now());
+        self.current_index = 0;
+        self.hits = 0;
+        self.misses = 0;
+        self.drill_timeouts.clear();
+        self.stats = SessionStats::start();
+        self.screen = Screen::Study;
+        self.reset_card();
+    }
+
+    /// Review: takes learning cards that are not due yet out of the queue, to
+    /// come back once their step has passed
+    fn hold_back_learning(&mut self, now: u64) {
+        self.learning_wait.clear();
+        if self.session != SessionMode::Review {
+            return;
+        }
+        let cards = &self.cards;
+        let (waiting, due): (Vec<usize>, Vec<usize>) = self
+            .due_cards
+            .iter()
+            .partition(|&&i| cards[i].schedule.stage() == Stage::Learning && !cards[i].schedule.is_due(now));
+        self.due_cards = due;
+        for i in waiting {
+            self.wait_for_step(i);
         }
     }
 
+    /// Puts a learning card in line to come back when it is due
+    fn wait_for_step(&mut self, i: usize) {
+        let cards = &self.cards;
+        let due = cards[i].schedule.due;
+        let at = self.learning_wait.partition_point(|&j| cards[j].schedule.due <= due);
+        self.learning_wait.insert(at, i);
+    }
+
+    /// Moves learning cards whose step has passed back into the queue, right
+    /// after the card on screen so it is not swapped out mid-answer
+    fn release_learning_cards(&mut self) {
+        let now = scheduler::now();
+        let cards = &self.cards;
+        let ready = self.learning_wait.iter().take_while(|&&i| cards[i].schedule.is_due(now)).count();
+        if ready == 0 {
+            return;
+        }
+
+        let ready: Vec<usize> = self.learning_wait.drain(..ready).collect();
+        if self.due_cards.is_empty() {
+            self.due_cards = ready;
+            self.current_index = 0;
+            self.reset_card();
+        } else {
+            let at = (self.current_index + 1).min(self.due_cards.len());
+            self.due_cards.splice(at..at, ready);
+        }
+    }
+
+    /// Time until the next learning card is due, if one is waiting
+    fn next_learning_in(&self) -> Option<Duration> {
+        let &i = self.learning_wait.first()?;
+        Some(Duration::from_secs(self.cards[i].schedule.due.saturating_sub(scheduler::now())))
+    }
+
+    /// Returns true for sessions that end on the summary screen. Exams have
+    /// their own results, and matching rounds and slideshows are not graded.
+    fn has_summary(&self) -> bool {
+        matches!(self.session, SessionMode::Review | SessionMode::Cram | SessionMode::Drill)
+    }
+
+    /// Switches to the summary screen, if anything was answered
+    fn show_summary(&mut self) {
+        if self.has_summary() && !self.stats.is_empty() {
+            self.stats.finish();
+            self.screen = Screen::Summary;
+        }
+    }
+
+    /// Leaves the summary for the cards that are left, if there are any
+    fn back_to_cards(&mut self) {
+        if !self.due_cards.is_empty() {
+            self.stats.resume();
+            self.screen = Screen::Study;
+        }
+    }
+
+    /// Starts a new session with only the cards missed in this one
+    fn study_missed(&mut self) {
+        let missed = self.stats.missed();
+        if missed.is_empty() {
+            return;
+        }
+
+        self.due_cards = missed;
+        self.hold_back_learning(scheduler::now());
+        self.current_index = 0;
+        self.hits = 0;
+        self.misses = 0;
+        self.drill_timeouts.clear();
+        self.stats = SessionStats::start();
+        self.screen = Screen::Study;
+        self.reset_card();
+    }
+
+    /// Clears everything tied to the card on screen before showing another
+    fn reset_card(&mut self) {
+        self.is_flipped = false;
+        self.card_shown = Instant::now();
+        self.timed_out = false;
+        self.hint_shown = false;
+        // A paused slideshow stays paused on the new card, with its full time ahead
+        if self.paused_at.is_some() {
+            self.paused_at = Some(Instant::now());
+        }
+        self.typed_answer.clear();
+        self.answer_check = None;
+        // A slideshow only shows cards, so there is nothing to choose from
+        self.choice = if self.mode == StudyMode::MultipleChoice && self.session != SessionMode::Slideshow {
+            self.build_choice()
+        } else {
+            None
+        };
+    }
+
+    /// Switches how cards are answered
+    fn set_mode(&mut self, mode: StudyMode) {
+        self.mode = mode;
+        self.reset_card();
+    }
+
+    /// Builds a multiple-choice question for the current card, taking
+    /// distractors from the other cards of its deck studied in the same
+    /// direction, so a mixed session never offers another subject's answers
+    fn build_choice(&self) -> Option<MultipleChoice> {
+        let card = self.current_card()?;
+        let others: Vec<&str> = self
+            .cards
+            .iter()
+            .filter(|other| other.deck == card.deck && other.reversed == card.reversed)
+            .filter(|other| other.id != card.id)
+            .map(|other| other.expected_answer())
+            .collect();
+
+        Some(MultipleChoice::new(card.expected_answer(), &others, &mut rand::rng()))
+    }
+
+    /// Picks a multiple-choice option, scores it and reveals the answer
+    fn choose_option(&mut self, index: usize) {
+        let choice = match self.choice.as_mut() {
+            Some(choice) if choice.chosen.is_none() && index < choice.options.len() => choice,
+            _ => return,
+        };
+
+        choice.chosen = Some(index);
+        self.quiz_answered += 1;
+        if choice.is_correct() {
+            self.quiz_correct += 1;
+        }
+        self.is_flipped = true;
+    }
+
+    /// Shuffles the deck; the same seed always gives the same order
+    fn shuffle(&mut self, seed: u64) {
+        self.cards.sort_by_key(|card| card.position);
+        self.cards.shuffle(&mut StdRng::seed_from_u64(seed));
+        self.shuffle_seed = Some(seed);
+        self.rebuild_due_cards();
+    }
+
+    /// Today's queue across every deck in the session: cards due before the
+    /// day ends (learning and review), then new cards. Reviews and new cards
+    /// are capped per deck and for the session as a whole, counting what was
+    /// already studied today.
+    fn build_daily_queue(&self, now: u64) -> Vec<usize> {
+        let (today, tomorrow) = scheduler::study_day(now, self.rollover_hour);
+        let cards = &self.cards;
+
+        // What is left of each cap once today's earlier studying is taken off
+        let mut new_left: Vec<usize> = Vec::new();
+        let mut reviews_left: Vec<usize> = Vec::new();
+        let mut total_new_left = self.total_limits.new_cards;
+        let mut total_reviews_left = self.total_limits.reviews;
+        for (d, deck) in self.decks.iter().enumerate() {
+            let (new_today, reviews_today) = self.studied_today(d, today);
+            new_left.push(deck.limits.new_cards.saturating_sub(new_today));
+            reviews_left.push(deck.limits.reviews.saturating_sub(reviews_today));
+            total_new_left = total_new_left.saturating_sub(new_today);
+            total_reviews_left = total_reviews_left.saturating_sub(reviews_today);
+        }
+
+        let studied: Vec<usize> = (0..cards.len()).filter(|&i| self.is_studied(&cards[i])).collect();
+
+        let mut queue: Vec<usize> = studied
+            .iter()
+            .copied()
+            .filter(|&i| cards[i].schedule.stage() != Stage::New)
+            .filter(|&i| cards[i].schedule.is_due(tomorrow - 1))
+            .collect();
+        queue.sort_by_key(|&i| self.deck_of(&cards[i]).scheduler.queue_order(&cards[i].schedule));
+        queue.retain(|&i| {
+            // Cards in their learning steps are never held back
+            let card = &cards[i];
+            card.schedule.stage() == Stage::Learning
+                || take_from_caps(&mut [&mut reviews_left[card.deck], &mut total_reviews_left])
+        });
+
+        queue.extend(studied.iter().copied().filter(|&i| {
+            let card = &cards[i];
+            card.schedule.stage() == Stage::New
+                && take_from_caps(&mut [&mut new_left[card.deck], &mut total_new_left])
+        }));
+        queue
+    }
+
+    /// Cards left in the queue as (due for review, new, learning)
+    fn remaining_by_stage(&self) -> (usize, usize, usize) {
+        let mut counts = (0, 0, 0);
+        for &i in self.due_cards.iter().chain(&self.learning_wait) {
+            match self.cards[i].schedule.stage() {
+                Stage::Review => counts.0 += 1,
+                Stage::New => counts.1 += 1,
+                Stage::Learning => counts.2 += 1,
+            }
+        }
+        counts
+    }
+
+    /// Cards from deck `d` studied since `today` began: (first studied today, reviewed today)
+    fn studied_today(&self, d: usize, today: u64) -> (usize, usize) {
+        let mut new_cards = 0;
+        let mut reviews = 0;
+        for card in self.cards.iter().filter(|card| card.deck == d) {
+            let history = &card.schedule.history;
+            match history.first() {
+                Some(first) if first.time >= today => new_cards += 1,
+                Some(_) if history.iter().any(|review| review.time >= today) => reviews += 1,
+                _ => {}
+            }
+        }
+        (new_cards, reviews)
+    }
+
+    /// The deck a card comes from
+    fn deck_of(&self, card: &Flashcard) -> &StudyDeck {
+        &self.decks[card.deck]
+    }
+
+    /// Switches the kind of session and starts it over
+    fn set_session(&mut self, session: SessionMode) {
+        self.session = session;
+        self.paused_at = None;
+        self.rebuild_due_cards();
+    }
+
+    /// Switches which side of the cards is studied first
+    fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+        self.rebuild_due_cards();
+    }
+
+    /// Returns true if the card is studied in the current direction.
+    /// Cloze cards have no reverse side, so they are always studied.
+    fn is_studied(&self, card: &Flashcard) -> bool {
+        card.hidden.is_some() || self.direction.includes(card.reversed)
+    }
+
+    /// Number of cards studied in the current direction, due or not
+    fn studied_card_count(&self) -> usize {
+        self.cards.iter().filter(|card| self.is_studied(card)).count()
+    }
+
+    /// Puts the cards back in the order they appear in the deck file
+    fn unshuffle(&mut self) {
+        self.cards.sort_by_key(|card| card.position);
+        self.shuffle_seed = None;
+        self.rebuild_due_cards();
+    }
+
+    /// Goes to the next card, or to the summary after the last one
     fn next_card(&mut self) {
-        if self.current_index < self.cards.len() - 1 {
+        if self.current_index + 1 < self.due_cards.len() {
             self.current_index += 1;
-            self.is_flipped = false;
+            self.reset_card();
+        } else {
+            self.show_summary();
         }
     }
 
+    /// Goes back a card; exams do not allow going back
     fn prev_card(&mut self) {
-        if self.current_index > 0 {
+        if self.current_index > 0 && self.session != SessionMode::Exam {
             self.current_index -= 1;
-            self.is_flipped = false;
+            self.reset_card();
         }
     }
 
+    /// Turns the card over; in an exam or drill the answer cannot be hidden again
     fn flip(&mut self) {
+        if self.current_card().is_none() {
+            return;
+        }
+        if self.is_flipped && matches!(self.session, SessionMode::Exam | SessionMode::Drill) {
+            return;
+        }
         self.is_flipped = !self.is_flipped;
     }
 
+    /// Returns the card currently being studied, if any are due
+    fn current_card(&self) -> Option<&Flashcard> {
+        self.due_cards
+            .get(self.current_index)
+            .map(|&i| &self.cards[i])
+    }
+
+    /// Records how well the current card was remembered and moves on.
+    /// The graded card is no longer due, so it leaves this session's list,
+    /// unless it is still in its learning steps and comes back later.
+    /// Once the last card is graded, the session ends on the summary screen.
+    fn grade_card(&mut self, grade: Grade) {
+        if let Some(&i) = self.due_cards.get(self.current_index) {
+            self.stats.record(i, grade, self.card_shown.elapsed());
+        }
+
+        match self.session {
+            SessionMode::Review => self.review_card(grade),
+            SessionMode::Cram => self.cram_card(grade),
+            SessionMode::Exam => self.exam_card(grade),
+            SessionMode::Drill => self.drill_card(grade),
+            // Matching rounds and slideshows are not graded card by card
+            SessionMode::Match | SessionMode::Slideshow => {}
+        }
+
+        if self.due_cards.is_empty() && self.learning_wait.is_empty() {
+            self.show_summary();
+        }
+    }
+
+    /// Review grading: updates the card's schedule and saves it
+    fn review_card(&mut self, grade: Grade) {
+        if let Some(&i) = self.due_cards.get(self.current_index) {
+            let card = &mut self.cards[i];
+            let deck = &mut self.decks[card.deck];
+            card.schedule
+                .record(deck.scheduler.as_ref(), &deck.learning_steps, grade, scheduler::now());
+            let learning = card.schedule.stage() == Stage::Learning;
+
+            deck.progress.update(&card.id, card.front(), &card.schedule);
+            if let Err(e) = deck.progress.save() {
+                eprintln!("Error saving progress: {}", e);
+            }
+
+            self.due_cards.remove(self.current_index);
+            if learning {
+                self.wait_for_step(i);
+            }
+            if self.current_index >= self.due_cards.len() {
+                self.current_index = self.due_cards.len().saturating_sub(1);
+            }
+            self.reset_card();
+        }
+    }
+
+    /// Cram grading: a pass takes the card out of the queue, a miss sends it
+    /// a few cards further back. The saved schedule is left alone.
+    fn cram_card(&mut self, grade: Grade) {
+        if self.current_index >= self.due_cards.len() {
+            return;
+        }
+
+        let i = self.due_cards.remove(self.current_index);
+        if !grade.is_pass() {
+            self.misses += 1;
+            let later = (self.current_index + CRAM_REQUEUE_GAP).min(self.due_cards.len());
+            self.due_cards.insert(later, i);
+        }
+
+        if self.current_index >= self.due_cards.len() {
+            self.current_index = 0;
+        }
+        self.reset_card();
+    }
+
+    /// Exam grading: any passing grade counts as right, Again as wrong.
+    /// Like cramming, an exam leaves the saved schedule alone.
+    fn exam_card(&mut self, grade: Grade) {
+        let exam = match self.exam.as_mut() {
+            Some(exam) if self.current_index < self.due_cards.len() => exam,
+            _ => return,
+        };
+
+        let i = self.due_cards.remove(self.current_index);
+        exam.record(i, grade.is_pass());
+
+        if self.current_index >= self.due_cards.len() {
+            self.current_index = 0;
+        }
+        self.reset_card();
+    }
+
+    /// Drill grading: a passing grade given in time is a hit, anything else a miss.
+    /// Each card is drilled once and the saved schedule is left alone.
+    fn drill_card(&mut self, grade: Grade) {
+        if self.current_index >= self.due_cards.len() {
+            return;
+        }
+
+        // A card that ran out of time was counted as missed when it did
+        let i = self.due_cards.remove(self.current_index);
+        if !self.drill_timeouts.contains(&i) {
+            if grade.is_pass() {
+                self.hits += 1;
+            } else {
+                self.misses += 1;
+            }
+        }
+
+        if self.current_index >= self.due_cards.len() {
+            self.current_index = 0;
+        }
+        self.reset_card();
+    }
+
+    /// Fraction of the drill countdown left for the current card, while it is running
+    fn drill_time_left(&self) -> Option<f32> {
+        if self.session != SessionMode::Drill || self.screen != Screen::Study {
+            return None;
+        }
+        if self.is_flipped || self.current_card().is_none() {
+            return None;
+        }
+        let elapsed = self.card_shown.elapsed().as_secs_f32();
+        Some((1.0 - elapsed / self.drill_limit.as_secs_f32()).max(0.0))
+    }
+
+    /// Reveals the answer when the drill countdown runs out and counts the
+    /// card as missed, whether or not it is graded afterwards
+    fn check_drill_time(&mut self) {
+        if !self.show_problems && self.drill_time_left() == Some(0.0) {
+            self.timed_out = true;
+            self.is_flipped = true;
+            let i = self.due_cards[self.current_index];
+            if !self.drill_timeouts.contains(&i) {
+                self.drill_timeouts.push(i);
+                self.misses += 1;
+            }
+        }
+    }
+
+    /// Slideshow timing: flips the card once `slide_flip_delay` has passed and
+    /// moves on after `slide_next_delay` more, looping or stopping at the end
+    fn advance_slideshow(&mut self) {
+        if self.session != SessionMode::Slideshow || self.paused_at.is_some() || self.show_problems || self.current_card().is_none() {
+            return;
+        }
+
+        let elapsed = self.card_shown.elapsed();
+        if !self.is_flipped && elapsed >= self.slide_flip_delay {
+            self.is_flipped = true;
+        } else if self.is_flipped && elapsed >= self.slide_flip_delay + self.slide_next_delay {
+            if self.current_index + 1 < self.due_cards.len() {
+                self.next_card();
+            } else if self.slide_loop {
+                self.current_index = 0;
+                self.reset_card();
+            }
+        }
+    }
+
+    /// Pauses or resumes the slideshow, keeping the time already spent on the card
+    fn toggle_pause(&mut self) {
+        match self.paused_at.take() {
+            Some(paused_at) => self.card_shown += paused_at.elapsed(),
+            None => self.paused_at = Some(Instant::now()),
+        }
+    }
+
+    /// Opens or closes the deck problems panel. The drill countdown and the
+    /// slideshow stand still while it is open.
+    fn set_show_problems(&mut self, show: bool) {
+        if show {
+            self.problems_opened_at.get_or_insert_with(Instant::now);
+        } else if let Some(opened_at) = self.problems_opened_at.take() {
+            let open_for = opened_at.elapsed();
+            self.card_shown += open_for;
+            if let Some(paused_at) = self.paused_at.as_mut() {
+                *paused_at += open_for;
+            }
+        }
+        self.show_problems = show;
+    }
+
+    /// Returns true once a non-looping slideshow has shown its last answer
+    fn slideshow_ended(&self) -> bool {
+        self.session == SessionMode::Slideshow
+            && !self.slide_loop
+            && self.is_flipped
+            && self.current_index + 1 >= self.due_cards.len()
+            && self.card_shown.elapsed() >= self.slide_flip_delay + self.slide_next_delay
+    }
+
+    /// Ends the exam when its time limit runs out
+    fn check_exam_time(&mut self) {
+        if self.exam.as_mut().is_some_and(|exam| exam.check_time()) {
+            self.due_cards.clear();
+            self.current_index = 0;
+            self.reset_card();
+        }
+    }
+
+    /// Checks the typed answer against the current card and reveals the answer
+    fn submit_typed_answer(&mut self) {
+        if let Some(card) = self.current_card() {
+            let options = &self.deck_of(card).match_options;
+            let check = check_answer(&self.typed_answer, card.expected_answer(), options);
+            self.answer_check = Some(check);
+            self.is_flipped = true;
+        }
+    }
+
+    /// Grade suggested by the typed-answer check or multiple-choice pick, if there was one
+    fn suggested_grade(&self) -> Option<Grade> {
+        if self.timed_out {
+            return Some(Grade::Again);
+        }
+        let correct = match (&self.answer_check, &self.choice) {
+            (Some(check), _) => check.correct,
+            (None, Some(choice)) if choice.chosen.is_some() => choice.is_correct(),
+            _ => return None,
+        };
+        Some(if correct { Grade::Good } else { Grade::Again })
+    }
+
     fn get_current_text(&self) -> &str {
-        if let Some(card) = self.cards.get(self.current_index) {
+        if let Some(card) = self.current_card() {
             if self.is_flipped {
-                &card.answer
+                card.back()
             } else {
-                &card.question
+                card.front()
             }
         } else {
             ""
@@ -58,97 +871,280 @@ impl FlashcardGame {
     }
 }
 
-fn parse_csv_line(line: &str) -> Option<(String, String)> {
-    let mut fields = Vec::new();
-    let mut current_field = String::new();
-    let mut in_quotes = false;
-    let mut chars = line.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        match c {
-            '"' => {
-                if in_quotes && chars.peek() == Some(&'"') {
-                    // Escaped quote (two quotes in a row)
-                    current_field.push('"');
-                    chars.next();
-                } else {
-                    // Toggle quote mode
-                    in_quotes = !in_quotes;
-                }
-            }
-            ',' if !in_quotes => {
-                // Field separator
-                fields.push(current_field.trim().to_string());
-                current_field.clear();
+/// Reads the cards in a deck file. Rows that can't become a card are left
+/// out, with the reason added to `problems`.
+fn load_flashcards(filename: &str, problems: &mut Vec<Diagnostic>) -> Result<Vec<Flashcard>, std::io::Error> {
+    let text = fs::read_to_string(filename)?;
+    let delimiter = deck_delimiter(filename, &text, problems);
+    let records = match csv::read_records(&text, delimiter) {
+        Ok(records) => records,
+        Err(e) => {
+            problems.push(Diagnostic::at(filename, e.line, e.column, e.reason));
+            return Ok(Vec::new());
+        }
+    };
+    // A header row names the columns; without one they are question, answer
+    let (columns, skip) = match records.first().map(|header| (header, ColumnMap::from_header(&header.fields))) {
+        Some((header, Some((columns, notes)))) => {
+            for (i, note) in notes {
+                let (line, column) = header.positions[i];
+                problems.push(Diagnostic::at(filename, line, column, note));
             }
-            _ => {
-                current_field.push(c);
+            (columns, 1)
+        }
+        _ => (ColumnMap::positional(), 0),
+    };
+    let mut cards: Vec<Flashcard> = Vec::new();
+    let mut used_ids = HashSet::new();
+
+    for record in records.into_iter().skip(skip) {
+        let at = |column| columns.index(column).and_then(|i| record.positions.get(i).copied());
+        let field = |column| columns.get(&record.fields, column).to_string();
+        let (Some(question_at), Some(answer_at)) = (at(Column::Question), at(Column::Answer)) else {
+            // Point just past the end of the row, where the missing field should be
+            let last = record.fields.len() - 1;
+            let (line, column) = record.positions[last];
+            let end = column + record.fields[last].chars().count();
+            let reason = if record.fields.len() == 1 {
+                "expected a question and an answer separated by a delimiter".to_string()
+            } else {
+                let missing = if at(Column::Question).is_none() { Column::Question } else { Column::Answer };
+                format!("row has no {} column", missing.name())
+            };
+            problems.push(Diagnostic::at(filename, line, end, reason));
+            continue;
+        };
+        let (question, answer) = (field(Column::Question), field(Column::Answer));
+        if question.is_empty() {
+            problems.push(Diagnostic::at(filename, question_at.0, question_at.1, "empty question"));
+            continue;
+        }
+
+        let hint = Some(field(Column::Hint)).filter(|hint| !hint.is_empty());
+        let notes = Some(field(Column::Notes)).filter(|notes| !notes.is_empty());
+        let tags: Vec<String> = field(Column::Tags)
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        // Use the ID column when present, otherwise hash the question
+        let explicit_id = Some(field(Column::Id)).filter(|id| !id.is_empty());
+        let base_id = explicit_id.unwrap_or_else(|| content_id(&question));
+        let id_at = at(Column::Id).unwrap_or(question_at);
+        let clozes = cloze::expand(&question);
+
+        if !clozes.is_empty() {
+            // One card per cloze index. The answer column is not needed, so
+            // any text in it is shown with the answer as notes instead
+            let notes = match (answer.is_empty(), notes) {
+                (true, notes) => notes,
+                (false, None) => Some(answer),
+                (false, Some(notes)) => Some(format!("{}\n{}", answer, notes)),
+            };
+            for cloze in clozes {
+                let id = unique_id(&mut used_ids, &format!("{}:c{}", base_id, cloze.index), filename, id_at, problems);
+                cards.push(Flashcard {
+                    id,
+                    position: cards.len(),
+                    question: cloze.front,
+                    answer: cloze.back,
+                    deck: 0,
+                    reversed: false,
+                    hidden: Some(cloze.hidden),
+                    highlights: cloze.spans,
+                    hint: hint.clone(),
+                    tags: tags.clone(),
+                    notes: notes.clone(),
+                    schedule: ReviewState::default(),
+                });
             }
+        } else if answer.is_empty() {
+            problems.push(Diagnostic::at(filename, answer_at.0, answer_at.1, "empty answer"));
+        } else {
+            cards.push(Flashcard {
+                id: unique_id(&mut used_ids, &base_id, filename, id_at, problems),
+                position: cards.len(),
+                question,
+                answer,
+                deck: 0,
+                reversed: false,
+                hidden: None,
+                highlights: Vec::new(),
+                hint,
+                tags,
+                notes,
+                schedule: ReviewState::default(),
+            });
         }
     }
 
-    // Add the last field
-    fields.push(current_field.trim().to_string());
+    Ok(cards)
+}
 
-    // Return first two fields as question and answer
-    if fields.len() >= 2 {
-        Some((fields[0].clone(), fields[1].clone()))
-    } else {
-        None
+/// Picks the field delimiter for a deck: the `delimiter` setting, or a guess
+/// from the file's first lines when it is `auto` (the default). Ties go to a
+/// comma for `.csv` files and a tab otherwise, as most exported lists are
+/// tab-separated.
+fn deck_delimiter(filename: &str, text: &str, problems: &mut Vec<Diagnostic>) -> char {
+    let preferred = if filename.ends_with(".csv") { ',' } else { '\t' };
+    let settings = DeckSettings::load(filename);
+    match settings.get_str("delimiter").unwrap_or("auto") {
+        name if name.eq_ignore_ascii_case("auto") => csv::sniff_delimiter(text, preferred),
+        name => csv::parse_delimiter(name).unwrap_or_else(|| {
+            let reason = format!("unknown delimiter '{}', expected comma, tab, semicolon, pipe or auto", name);
+            problems.push(Diagnostic::file(filename, reason));
+            csv::sniff_delimiter(text, preferred)
+        }),
     }
 }
 
-fn load_flashcards(filename: &str) -> Result<Vec<Flashcard>, std::io::Error> {
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
-    let mut cards = Vec::new();
+/// Returns `base_id`, suffixed if needed so two rows never share an ID, and
+/// adds it to `used`
+fn unique_id(
+    used: &mut HashSet<String>,
+    base_id: &str,
+    filename: &str,
+    (line, column): (usize, usize),
+    problems: &mut Vec<Diagnostic>,
+) -> String {
+    let mut id = base_id.to_string();
+    let mut copy = 1;
+    while used.contains(&id) {
+        copy += 1;
+        id = format!("{}-{}", base_id, copy);
+    }
+    if copy > 1 {
+        let reason = format!("duplicate card ID '{}', using '{}'", base_id, id);
+        problems.push(Diagnostic::at(filename, line, column, reason));
+    }
+    used.insert(id.clone());
+    id
+}
 
-    for line in reader.lines() {
-        let line = line?;
+/// Matches saved progress to the deck's cards after edits, reporting what changed
+fn reconcile_progress(progress: &mut ProgressStore, cards: &[Flashcard]) {
+    let keys: Vec<(&str, &str)> = cards
+        .iter()
+        .map(|card| (card.id.as_str(), card.front()))
+        .collect();
+    let report = progress.reconcile(&keys);
 
-        if let Some((question, answer)) = parse_csv_line(&line) {
-            if !question.is_empty() && !answer.is_empty() {
-                cards.push(Flashcard { question, answer });
-            }
+    for (old, new) in &report.remapped {
+        if old == new {
+            eprintln!("Progress: card \"{}\" has a new ID", new);
+        } else {
+            eprintln!("Progress: matched edited card \"{}\" to \"{}\"", old, new);
+        }
+    }
+    if !report.orphaned.is_empty() {
+        eprintln!(
+            "Warning: {} saved progress entries no longer match any card:",
+            report.orphaned.len()
+        );
+        for question in &report.orphaned {
+            eprintln!("  - {}", question);
         }
     }
 
-    Ok(cards)
+    // Persist remapped entries so the edited cards keep their new IDs
+    if report.remapped.is_empty() {
+        return;
+    }
+    if let Err(e) = progress.save() {
+        eprintln!("Error saving progress: {}", e);
+    }
 }
 
 fn wrap_text(text: &str, max_width: i32, font_size: i32) -> Vec<String> {
-    let words: Vec<&str> = text.split_whitespace().collect();
     let mut lines = Vec::new();
-    let mut current_line = String::new();
     let approx_char_width = font_size / 2;
 
-    for word in words {
-        let test_line = if current_line.is_empty() {
-            word.to_string()
-        } else {
-            format!("{} {}", current_line, word)
-        };
+    // Line breaks in the text are kept, and each paragraph is wrapped on its own
+    for paragraph in text.split('\n') {
+        let mut current_line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let test_line = if current_line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", current_line, word)
+            };
 
-        if (test_line.len() as i32 * approx_char_width) > max_width {
-            if !current_line.is_empty() {
-                lines.push(current_line);
-                current_line = word.to_string();
+            if (test_line.len() as i32 * approx_char_width) > max_width {
+                if !current_line.is_empty() {
+                    lines.push(current_line);
+                    current_line = word.to_string();
+                } else {
+                    lines.push(word.to_string());
+                }
             } else {
-                lines.push(word.to_string());
+                current_line = test_line;
             }
-        } else {
-            current_line = test_line;
         }
-    }
 
-    if !current_line.is_empty() {
-        lines.push(current_line);
+        // A blank paragraph stays as an empty line
+        if !current_line.is_empty() || paragraph.trim().is_empty() {
+            lines.push(current_line);
+        }
     }
 
     lines
 }
 
 
+/// Like `wrap_text`, but keeps track of which characters fall inside the
+/// `highlights` byte ranges. Each line is a list of (text, highlighted) pieces.
+fn wrap_highlighted(text: &str, highlights: &[(usize, usize)], max_width: i32, font_size: i32) -> Vec<Vec<(String, bool)>> {
+    let approx_char_width = font_size / 2;
+    let is_highlighted = |i: usize| highlights.iter().any(|&(start, end)| start <= i && i < end);
+
+    // Adds text to the line, merging it with the last piece if the style matches
+    fn push_piece(line: &mut Vec<(String, bool)>, text: &str, highlighted: bool) {
+        match line.last_mut() {
+            Some((last, last_highlighted)) if *last_highlighted == highlighted => last.push_str(text),
+            _ => line.push((text.to_string(), highlighted)),
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut paragraph_start = 0;
+
+    // Line breaks in the text are kept, and each paragraph is wrapped on its own
+    for paragraph in text.split('\n') {
+        let mut line: Vec<(String, bool)> = Vec::new();
+        let mut line_len = 0;
+        let mut offset = 0;
+
+        for word in paragraph.split_whitespace() {
+            // Byte offset of this word in the original text
+            let start = offset + paragraph[offset..].find(word).unwrap_or(0);
+            offset = start + word.len();
+            let start = paragraph_start + start;
+
+            let word_len = word.len() as i32;
+            if line_len > 0 && (line_len + 1 + word_len) * approx_char_width > max_width {
+                lines.push(std::mem::take(&mut line));
+                line_len = 0;
+            }
+
+            if line_len > 0 {
+                // The space is highlighted when it sits inside a multi-word span
+                push_piece(&mut line, " ", is_highlighted(start - 1));
+                line_len += 1;
+            }
+            for (i, c) in word.char_indices() {
+                push_piece(&mut line, c.encode_utf8(&mut [0; 4]), is_highlighted(start + i));
+            }
+            line_len += word_len;
+        }
+
+        lines.push(line);
+        paragraph_start += paragraph.len() + 1;
+    }
+
+    lines
+}
+
 fn draw_text_centered(
     d: &mut RaylibDrawHandle,
     custom_font: &Option<Font>,
@@ -174,40 +1170,486 @@ fn draw_text_centered(
     }
 }
 
-fn try_load_cards(filename: &str) -> Option<Vec<Flashcard>> {
-    match load_flashcards(filename) {
+/// Width of `text` in pixels when drawn with `draw_text_centered`
+fn measure_text_width(d: &RaylibDrawHandle, custom_font: &Option<Font>, text: &str, font_size: f32) -> f32 {
+    if let Some(font) = custom_font {
+        font.measure_text(text, font_size, 0.0).x
+    } else {
+        d.measure_text(text, font_size as i32) as f32
+    }
+}
+
+/// Shrinks `font_size` until `text` fits in `max_width`, down to a readable minimum
+fn fit_font_size(d: &RaylibDrawHandle, custom_font: &Option<Font>, text: &str, max_width: f32, font_size: f32) -> f32 {
+    let mut size = font_size;
+    while size > 16.0 && measure_text_width(d, custom_font, text, size) > max_width {
+        size -= 1.0;
+    }
+    size
+}
+
+/// Draws differently colored pieces of text as one centered line
+fn draw_segments_centered(
+    d: &mut RaylibDrawHandle,
+    custom_font: &Option<Font>,
+    segments: &[(String, Color)],
+    center_x: i32,
+    y: i32,
+    font_size: f32,
+) {
+    let widths: Vec<f32> = segments
+        .iter()
+        .map(|(text, _)| measure_text_width(d, custom_font, text, font_size))
+        .collect();
+    let mut x = center_x as f32 - widths.iter().sum::<f32>() / 2.0;
+
+    for ((text, color), width) in segments.iter().zip(widths) {
+        if let Some(font) = custom_font {
+            d.draw_text_ex(font, text, Vector2::new(x, y as f32), font_size, 0.0, *color);
+        } else {
+            d.draw_text(text, x as i32, y, font_size as i32, *color);
+        }
+        x += width;
+    }
+}
+
+/// Draws the typed-answer input box with a cursor
+fn draw_answer_input(d: &mut RaylibDrawHandle, custom_font: &Option<Font>, typed: &str, y: i32, font_size: f32) {
+    let input_rect = Rectangle::new(150.0, y as f32 - 8.0, 500.0, font_size + 16.0);
+    d.draw_rectangle_rounded(input_rect, 0.3, 10, Color::from_hex("ECF0F1").unwrap());
+    d.draw_rectangle_rounded_lines(input_rect, 0.3, 10, Color::from_hex("3498DB").unwrap());
+
+    let text = format!("{}_", typed);
+    let size = fit_font_size(d, custom_font, &text, 480.0, font_size);
+    draw_text_centered(d, custom_font, &text, 400, y, size, Color::from_hex("2C3E50").unwrap());
+}
+
+/// Draws the verdict and a character-level diff of the typed answer:
+/// extra characters in red, missing ones in yellow
+fn draw_answer_diff(d: &mut RaylibDrawHandle, custom_font: &Option<Font>, check: &AnswerCheck, y: i32, font_size: f32) {
+    let (verdict, verdict_color) = if check.correct {
+        ("Correct!  ", Color::from_hex("2ECC71").unwrap())
+    } else {
+        ("Not quite:  ", Color::from_hex("F5B7B1").unwrap())
+    };
+
+    let mut segments = vec![(verdict.to_string(), verdict_color)];
+    if check.typed.trim().is_empty() {
+        segments.push(("(no answer)".to_string(), Color::WHITE));
+    } else {
+        for segment in &check.diff {
+            segments.push(match segment {
+                DiffSegment::Same(text) => (text.clone(), Color::WHITE),
+                DiffSegment::Extra(text) => (text.clone(), Color::from_hex("E74C3C").unwrap()),
+                DiffSegment::Missing(text) => (text.clone(), Color::from_hex("F1C40F").unwrap()),
+            });
+        }
+    }
+
+    let line: String = segments.iter().map(|(text, _)| text.as_str()).collect();
+    let size = fit_font_size(d, custom_font, &line, 560.0, font_size);
+    draw_segments_centered(d, custom_font, &segments, 400, y, size);
+}
+
+/// Screen areas of the multiple-choice options: a 2x2 grid in the lower part of the card
+fn option_rects() -> [Rectangle; quiz::OPTION_COUNT] {
+    [
+        Rectangle::new(115.0, 225.0, 280.0, 100.0),
+        Rectangle::new(405.0, 225.0, 280.0, 100.0),
+        Rectangle::new(115.0, 335.0, 280.0, 100.0),
+        Rectangle::new(405.0, 335.0, 280.0, 100.0),
+    ]
+}
+
+/// Draws a multiple-choice question on the card: the prompt at the top and
+/// numbered options below, colored once an option has been picked
+fn draw_multiple_choice(d: &mut RaylibDrawHandle, custom_font: &Option<Font>, prompt: &str, choice: &MultipleChoice) {
+    let dark = Color::from_hex("2C3E50").unwrap();
+
+    // Prompt, kept to the space above the options
+    let prompt_size = 30.0;
+    let lines = wrap_text(prompt, 560, prompt_size as i32);
+    let line_height = (prompt_size + 4.0) as i32;
+    let start_y = 165 - (lines.len().min(3) as i32 * line_height) / 2;
+    for (i, line) in lines.iter().take(3).enumerate() {
+        draw_text_centered(d, custom_font, line, 400, start_y + i as i32 * line_height, prompt_size, dark);
+    }
+
+    for (i, (option, rect)) in choice.options.iter().zip(option_rects()).enumerate() {
+        let fill = match choice.chosen {
+            Some(_) if i == choice.correct => Color::from_hex("2ECC71").unwrap(),
+            Some(chosen) if i == chosen => Color::from_hex("E74C3C").unwrap(),
+            _ => Color::from_hex("D6EAF8").unwrap(),
+        };
+        d.draw_rectangle_rounded(rect, 0.15, 10, fill);
+        d.draw_rectangle_rounded_lines(rect, 0.15, 10, Color::from_hex("34495E").unwrap());
+
+        let option_size = 22.0;
+        let text = format!("{}. {}", i + 1, option);
+        let lines = wrap_text(&text, rect.width as i32 - 30, option_size as i32);
+        let line_height = (option_size + 3.0) as i32;
+        let shown = lines.len().min(3) as i32;
+        let center_y = (rect.y + rect.height / 2.0) as i32;
+        let start_y = center_y - shown * line_height / 2;
+        let center_x = (rect.x + rect.width / 2.0) as i32;
+        for (j, line) in lines.iter().take(3).enumerate() {
+            draw_text_centered(d, custom_font, line, center_x, start_y + j as i32 * line_height, option_size, dark);
+        }
+    }
+}
+
+/// Draws the exam results on the card: score, time taken and the missed cards
+fn draw_exam_results(d: &mut RaylibDrawHandle, custom_font: &Option<Font>, exam: &Exam, cards: &[Flashcard]) {
+    let dark = Color::from_hex("2C3E50").unwrap();
+    let timed_out = exam.remaining().is_some_and(|remaining| remaining.is_zero());
+    let title = if timed_out { "Time's up!" } else { "Exam complete" };
+    draw_text_centered(d, custom_font, title, 400, 120, 40.0, dark);
+
+    let score = format!(
+        "Score: {} / {}  ({:.0}%)   Time: {}",
+        exam.correct_count(),
+        exam.cards.len(),
+        exam.percent(),
+        format_duration(exam.elapsed())
+    );
+    let size = fit_font_size(d, custom_font, &score, 560.0, 30.0);
+    draw_text_centered(d, custom_font, &score, 400, 170, size, dark);
+
+    let missed = exam.missed();
+    if missed.is_empty() {
+        draw_text_centered(d, custom_font, "No cards missed!", 400, 230, 26.0, Color::from_hex("27AE60").unwrap());
+        return;
+    }
+
+    // List as many missed cards as fit on the card
+    let shown = 6;
+    draw_text_centered(d, custom_font, "Missed:", 400, 220, 26.0, Color::from_hex("C0392B").unwrap());
+    for (row, &i) in missed.iter().take(shown).enumerate() {
+        let line = format!("{}  =  {}", cards[i].front(), cards[i].back());
+        let size = fit_font_size(d, custom_font, &line, 560.0, 22.0);
+        draw_text_centered(d, custom_font, &line, 400, 252 + row as i32 * 27, size, dark);
+    }
+    if missed.len() > shown {
+        let more = format!("... and {} more", missed.len() - shown);
+        draw_text_centered(d, custom_font, &more, 400, 252 + shown as i32 * 27, 22.0, dark);
+    }
+}
+
+/// Draws the drill countdown as a bar along the top of the card that shrinks
+/// and turns from green to red as time runs out
+fn draw_countdown_bar(d: &mut RaylibDrawHandle, card_rect: Rectangle, fraction: f32) {
+    let color = if fraction > 0.5 {
+        Color::from_hex("2ECC71").unwrap()
+    } else if fraction > 0.25 {
+        Color::from_hex("F39C12").unwrap()
+    } else {
+        Color::from_hex("E74C3C").unwrap()
+    };
+    let track = Rectangle::new(card_rect.x + 20.0, card_rect.y + 12.0, card_rect.width - 40.0, 10.0);
+    d.draw_rectangle_rounded(track, 1.0, 6, Color::from_hex("BDC3C7").unwrap());
+    let bar = Rectangle::new(track.x, track.y, track.width * fraction, track.height);
+    d.draw_rectangle_rounded(bar, 1.0, 6, color);
+}
+
+/// Screen area of a matching tile: questions on the left, answers on the right,
+/// `count` rows filling the card area
+fn tile_rect(is_answer: bool, index: usize, count: usize) -> Rectangle {
+    let gap = 8.0;
+    let height = (350.0 - gap * (count as f32 - 1.0)) / count as f32;
+    let x = if is_answer { 410.0 } else { 100.0 };
+    Rectangle::new(x, 100.0 + index as f32 * (height + gap), 290.0, height)
+}
+
+/// Draws the tiles of a matching round, and the result once every pair is matched
+fn draw_matching(d: &mut RaylibDrawHandle, custom_font: &Option<Font>, round: &MatchingRound) {
+    let dark = Color::from_hex("2C3E50").unwrap();
+    let count = round.questions.len();
+    let columns = [(false, &round.questions), (true, &round.answers)];
+
+    for (is_answer, tiles) in columns {
+        for (i, tile) in tiles.iter().enumerate() {
+            let rect = tile_rect(is_answer, i, count);
+            let fill = if tile.matched {
+                Color::from_hex("2ECC71").unwrap()
+            } else if round.is_flashing_wrong(is_answer, i) {
+                Color::from_hex("E74C3C").unwrap()
+            } else if round.selected == Some((is_answer, i)) {
+                Color::from_hex("F9E79F").unwrap()
+            } else if is_answer {
+                Color::from_hex("D6EAF8").unwrap()
+            } else {
+                Color::from_hex("ECF0F1").unwrap()
+            };
+            d.draw_rectangle_rounded(rect, 0.15, 10, fill);
+            d.draw_rectangle_rounded_lines(rect, 0.15, 10, Color::from_hex("34495E").unwrap());
+
+            let size = 20.0;
+            let line_height = (size + 3.0) as i32;
+            let max_lines = ((rect.height as i32 - 6) / line_height).max(1) as usize;
+            let lines = wrap_text(&tile.text, rect.width as i32 - 20, size as i32);
+            let shown = lines.len().min(max_lines) as i32;
+            let start_y = (rect.y + rect.height / 2.0) as i32 - shown * line_height / 2;
+            let center_x = (rect.x + rect.width / 2.0) as i32;
+            for (j, line) in lines.iter().take(max_lines).enumerate() {
+                draw_text_centered(d, custom_font, line, center_x, start_y + j as i32 * line_height, size, dark);
+            }
+        }
+    }
+
+    if round.is_finished() {
+        let banner = Rectangle::new(200.0, 215.0, 400.0, 120.0);
+        d.draw_rectangle_rounded(banner, 0.15, 10, Color::from_hex("ECF0F1").unwrap());
+        d.draw_rectangle_rounded_lines(banner, 0.15, 10, Color::from_hex("34495E").unwrap());
+        draw_text_centered(d, custom_font, "All matched!", 400, 235, 40.0, dark);
+        let result = format!(
+            "Time {}  |  {} mistakes",
+            format_duration(round.elapsed()),
+            round.mistakes
+        );
+        draw_text_centered(d, custom_font, &result, 400, 290, 26.0, dark);
+    }
+}
+
+/// Screen areas of the summary's buttons: restart, study missed, next deck
+fn summary_button_rects() -> [Rectangle; 3] {
+    [
+        Rectangle::new(115.0, 375.0, 180.0, 55.0),
+        Rectangle::new(310.0, 375.0, 180.0, 55.0),
+        Rectangle::new(505.0, 375.0, 180.0, 55.0),
+    ]
+}
+
+/// Draws the end-of-session summary on the card: cards seen, time spent,
+/// answers by grade, the slowest cards and buttons for what to do next
+fn draw_summary(d: &mut RaylibDrawHandle, custom_font: &Option<Font>, stats: &SessionStats, cards: &[Flashcard]) {
+    let dark = Color::from_hex("2C3E50").unwrap();
+    let card_rect = Rectangle::new(100.0, 100.0, 600.0, 350.0);
+    d.draw_rectangle_rounded(card_rect, 0.05, 10, Color::from_hex("ECF0F1").unwrap());
+    d.draw_rectangle_rounded_lines(card_rect, 0.05, 10, Color::from_hex("34495E").unwrap());
+
+    draw_text_centered(d, custom_font, "Session summary", 400, 112, 36.0, dark);
+    let overview = format!(
+        "Cards seen: {}  |  Answers: {}  |  Time: {}",
+        stats.cards_seen(),
+        stats.answer_count(),
+        format_duration(stats.elapsed())
+    );
+    let size = fit_font_size(d, custom_font, &overview, 560.0, 24.0);
+    draw_text_centered(d, custom_font, &overview, 400, 160, size, dark);
+
+    // Answers by grade, in the grading colors
+    let colors = ["E74C3C", "E67E22", "27AE60", "2E86C1"];
+    let counts = stats.grade_counts();
+    let total = stats.answer_count().max(1);
+    for (i, grade) in Grade::ALL.iter().enumerate() {
+        let label = format!("{} {} ({}%)", grade.label(), counts[i], counts[i] * 100 / total);
+        let color = Color::from_hex(colors[i]).unwrap();
+        draw_text_centered(d, custom_font, &label, 175 + 150 * i as i32, 198, 22.0, color);
+    }
+
+    draw_text_centered(d, custom_font, "Slowest cards:", 400, 236, 22.0, dark);
+    for (row, (i, time)) in stats.slowest(3).into_iter().enumerate() {
+        let line = format!("{:.1}s  {}", time.as_secs_f32(), cards[i].front());
+        let size = fit_font_size(d, custom_font, &line, 560.0, 20.0);
+        draw_text_centered(d, custom_font, &line, 400, 264 + row as i32 * 26, size, dark);
+    }
+
+    let missed = stats.missed().len();
+    let labels = [
+        "1. Restart".to_string(),
+        format!("2. Missed ({})", missed),
+        "3. Next deck".to_string(),
+    ];
+    for (i, (label, rect)) in labels.iter().zip(summary_button_rects()).enumerate() {
+        // Studying missed cards is only possible if some were missed
+        let enabled = i != 1 || missed > 0;
+        let fill = if enabled { "D6EAF8" } else { "E5E8E8" };
+        d.draw_rectangle_rounded(rect, 0.3, 10, Color::from_hex(fill).unwrap());
+        d.draw_rectangle_rounded_lines(rect, 0.3, 10, Color::from_hex("34495E").unwrap());
+        let color = if enabled { dark } else { Color::from_hex("95A5A6").unwrap() };
+        let center_x = (rect.x + rect.width / 2.0) as i32;
+        let center_y = (rect.y + rect.height / 2.0) as i32;
+        draw_text_centered(d, custom_font, label, center_x, center_y - 12, 22.0, color);
+    }
+}
+
+/// Draws the deck problems panel over the card: one line per problem, as many as fit
+fn draw_problems(d: &mut RaylibDrawHandle, custom_font: &Option<Font>, problems: &[Diagnostic]) {
+    let dark = Color::from_hex("2C3E50").unwrap();
+    let card_rect = Rectangle::new(100.0, 100.0, 600.0, 350.0);
+    d.draw_rectangle_rounded(card_rect, 0.05, 10, Color::from_hex("ECF0F1").unwrap());
+    d.draw_rectangle_rounded_lines(card_rect, 0.05, 10, Color::from_hex("34495E").unwrap());
+
+    let title = format!("Deck problems ({})", problems.len());
+    draw_text_centered(d, custom_font, &title, 400, 120, 34.0, Color::from_hex("C0392B").unwrap());
+    if problems.is_empty() {
+        draw_text_centered(d, custom_font, "Every row loaded fine.", 400, 230, 26.0, Color::from_hex("27AE60").unwrap());
+        return;
+    }
+
+    let shown = 9;
+    for (row, problem) in problems.iter().take(shown).enumerate() {
+        let line = problem.to_string();
+        let size = fit_font_size(d, custom_font, &line, 560.0, 20.0);
+        draw_text_centered(d, custom_font, &line, 400, 165 + row as i32 * 28, size, dark);
+    }
+    if problems.len() > shown {
+        let more = format!("... and {} more, listed in the console", problems.len() - shown);
+        draw_text_centered(d, custom_font, &more, 400, 165 + shown as i32 * 28, 20.0, dark);
+    }
+}
+
+/// Draws the grading options (1-4) in a row, each in its own color
+fn draw_grade_options(d: &mut RaylibDrawHandle, custom_font: &Option<Font>, y: i32, font_size: f32) {
+    let colors = ["E74C3C", "E67E22", "2ECC71", "5DADE2"];
+    let spacing = 150;
+    let start_x = 400 - spacing * (Grade::ALL.len() as i32 - 1) / 2;
+
+    for (i, grade) in Grade::ALL.iter().enumerate() {
+        let label = format!("{} {}", i + 1, grade.label());
+        let color = Color::from_hex(colors[i]).unwrap();
+        draw_text_centered(d, custom_font, &label, start_x + spacing * i as i32, y, font_size, color);
+    }
+}
+
+/// Counter detail for a card: new, its learning step, or the scheduler's label once it has graduated
+fn stage_label(deck: &StudyDeck, card: &Flashcard) -> Option<String> {
+    match (card.schedule.stage(), card.schedule.learning_step) {
+        (Stage::Learning, Some(step)) => Some(format!(
+            "{} {} / {}",
+            Stage::Learning.label(),
+            step + 1,
+            deck.learning_steps.len()
+        )),
+        (Stage::Review, _) => deck.scheduler.card_label(&card.schedule),
+        (stage, _) => Some(stage.label().to_string()),
+    }
+}
+
+fn try_load_cards(filename: &str, problems: &mut Vec<Diagnostic>) -> Option<Vec<Flashcard>> {
+    match load_flashcards(filename, problems) {
         Ok(cards) if !cards.is_empty() => Some(cards),
         Ok(_) => {
-            eprintln!("Error: cards.csv is empty or contains no valid flashcards");
+            problems.push(Diagnostic::file(filename, "no valid flashcards, deck skipped"));
             None
         }
         Err(e) => {
-            eprintln!("Error loading cards.csv: {}", e);
+            problems.push(Diagnostic::file(filename, format!("could not be read: {}", e)));
             None
         }
     }
 }
 
-fn update_decks(decks: &DeckManager, game: &mut FlashcardGame) {
-    match try_load_cards(&decks.get_current_deck_path()){
-        Some(cards) => *game = FlashcardGame::new(cards),
-        None => panic!("Failed to load cards")
+/// Builds a game for the current deck, or every deck mixed into the session,
+/// restoring their saved progress and applying their settings. Decks that
+/// fail to load are left out; if none of them load, returns what went wrong.
+fn new_game(decks: &DeckManager) -> Result<FlashcardGame, Vec<Diagnostic>> {
+    let session_decks = decks.session_decks();
+    let mut problems = Vec::new();
+    let mut loaded_paths = Vec::new();
+    let sources: Vec<(StudyDeck, Vec<Flashcard>)> = session_decks
+        .iter()
+        .filter_map(|(name, path)| {
+            let cards = try_load_cards(path, &mut problems)?;
+            loaded_paths.push(path);
+            Some((StudyDeck::load(name, path), cards))
+        })
+        .collect();
+    for problem in &problems {
+        eprintln!("Warning: {}", problem);
+    }
+    let Some(first_path) = loaded_paths.first() else {
+        return Err(problems);
+    };
+
+    // Session-wide options come from the first deck that loaded
+    let settings = DeckSettings::load(first_path);
+    let mut game = FlashcardGame::new(sources, &settings);
+    game.problems = problems;
+    Ok(game)
+}
+
+fn update_decks(decks: &DeckManager, game: &mut FlashcardGame, shuffle_seed: Option<u64>) {
+    let direction = game.direction;
+    let mode = game.mode;
+    let session = game.session;
+    match new_game(decks) {
+        Ok(new_game) => *game = new_game,
+        Err(problems) => {
+            // Stay on the current cards and show why the switch failed
+            game.problems = problems;
+            game.set_show_problems(true);
+            return;
+        }
+    }
+    game.set_mode(mode);
+    game.session = session;
+    game.set_direction(direction);
+    if let Some(seed) = shuffle_seed {
+        game.shuffle(seed);
     }
 }
 
 
 fn main() {
-    let mut decks: utils::DeckManager = utils::DeckManager::new("./flashcard_decks").unwrap();
-    //let cards = match load_flashcards("cards.csv") {
-    let maybe_cards = try_load_cards(&decks.get_current_deck_path());
-    if maybe_cards.is_none() {
-        panic!("Could not load cards from folder");
+    let options = match Options::from_args() {
+        Ok(options) => options,
+        Err(message) => {
+            // An empty message means --help was requested
+            if !message.is_empty() {
+                eprintln!("Error: {}", message);
+            }
+            eprintln!("{}", options::USAGE);
+            std::process::exit(if message.is_empty() { 0 } else { 2 });
+        }
+    };
+
+    if let Some(export) = &options.import_anki {
+        match anki::import(export, DECK_FOLDER) {
+            Ok(import) => {
+                for problem in &import.problems {
+                    eprintln!("Warning: {}", problem);
+                }
+                eprintln!("Imported {} notes into {}", import.notes, import.path);
+            }
+            Err(message) => {
+                eprintln!("Error: {}", message);
+                std::process::exit(1);
+            }
+        }
     }
 
-    let cards = maybe_cards.unwrap();
-   
+    let mut decks: utils::DeckManager = utils::DeckManager::new(DECK_FOLDER).unwrap();
+    if options.decks.is_empty() {
+        // Start with today's queue across every deck
+        decks.toggle_all_decks();
+    } else if let Err(message) = decks.select_decks(&options.decks) {
+        eprintln!("Error: {}", message);
+        std::process::exit(2);
+    }
+
+    //let cards = match load_flashcards("cards.csv") {
+    let mut game = match new_game(&decks) {
+        Ok(game) => game,
+        Err(_) => {
+            // The problems were already printed while loading
+            eprintln!("Error: no deck could be loaded");
+            std::process::exit(1);
+        }
+    };
+    game.set_mode(options.mode);
+    game.session = options.session;
+    game.set_direction(options.direction);
 
-    let mut game = FlashcardGame::new(cards);
+    // A fixed seed makes every shuffle in this session reproducible
+    let mut shuffle_seed = None;
+    if options.shuffle {
+        shuffle_seed = Some(options.seed.unwrap_or_else(rand::random));
+    }
+    if let Some(seed) = shuffle_seed {
+        eprintln!("Shuffling with seed {}", seed);
+        game.shuffle(seed);
+    }
 
     let (mut rl, thread) = raylib::init()
         .size(800, 600)
@@ -224,50 +1666,260 @@ fn main() {
     let signifier_color = Color::from_hex("95A5A6").unwrap();
 
     while !rl.window_should_close() {
+        game.check_exam_time();
+        game.check_drill_time();
+        game.advance_slideshow();
+        game.release_learning_cards();
+        let exam_finished = game.exam.as_ref().is_some_and(|exam| exam.is_finished());
+        let matching_finished = game.matching.as_ref().is_some_and(|round| round.is_finished());
+
         // Input handling
-        if rl.is_key_pressed(KeyboardKey::KEY_SPACE) || rl.is_key_pressed(KeyboardKey::KEY_UP) {
-            game.flip();
-        }
-        if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) {
-            game.next_card();
-        }
-        if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
-            game.prev_card();
-        }
-        if rl.is_key_pressed(KeyboardKey::KEY_A) {
-            decks.prev_deck();
-            update_decks(&decks, &mut game);
-        }
-        if rl.is_key_pressed(KeyboardKey::KEY_D) {
-            decks.next_deck();
-            update_decks(&decks, &mut game);
+        // While an answer is being typed, letter keys go to the input box
+        let typing = game.mode == StudyMode::Typed
+            && game.session != SessionMode::Slideshow
+            && !game.is_flipped
+            && game.current_card().is_some();
+        let choosing = game.choice.is_some() && !game.is_flipped;
+        let was_flipped = game.is_flipped;
+
+        if game.show_problems {
+            // Only closing the panel works while it is open
+            if rl.is_key_pressed(KeyboardKey::KEY_E) {
+                game.set_show_problems(false);
+            }
+        } else if game.screen == Screen::Summary {
+            // Pick what to do next with 1-3 or a click; LEFT goes back to the cards
+            let clicked = if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                let mouse = rl.get_mouse_position();
+                summary_button_rects().iter().position(|rect| rect.check_collision_point_rec(mouse))
+            } else {
+                None
+            };
+            let pressed = GRADE_KEYS.iter().take(3).position(|(key, _)| rl.is_key_pressed(*key));
+            match pressed.or(clicked) {
+                Some(0) => game.rebuild_due_cards(),
+                Some(1) => game.study_missed(),
+                Some(2) => {
+                    // Leave a mixed session for the deck after the cursor, studied alone
+                    decks.clear_selection();
+                    decks.next_deck();
+                    update_decks(&decks, &mut game, shuffle_seed);
+                }
+                _ => {}
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
+                game.back_to_cards();
+            }
+        } else {
+            if choosing {
+                // Number keys or a click pick an option
+                for (i, (key, _)) in GRADE_KEYS.iter().enumerate() {
+                    if rl.is_key_pressed(*key) {
+                        game.choose_option(i);
+                    }
+                }
+                if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                    let mouse = rl.get_mouse_position();
+                    if let Some(i) = option_rects().iter().position(|rect| rect.check_collision_point_rec(mouse)) {
+                        game.choose_option(i);
+                    }
+                }
+            }
+
+            if let Some(round) = game.matching.as_mut() {
+                // Clicking a question and then an answer (or the other way round) pairs them
+                if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+                    let mouse = rl.get_mouse_position();
+                    let count = round.questions.len();
+                    for is_answer in [false, true] {
+                        if let Some(i) = (0..count).position(|i| tile_rect(is_answer, i, count).check_collision_point_rec(mouse)) {
+                            round.select(is_answer, i);
+                        }
+                    }
+                }
+            }
+
+            if typing {
+                while let Some(c) = rl.get_char_pressed() {
+                    if !c.is_control() {
+                        game.typed_answer.push(c);
+                    }
+                }
+                if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) || rl.is_key_pressed_repeat(KeyboardKey::KEY_BACKSPACE) {
+                    game.typed_answer.pop();
+                }
+                if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                    game.submit_typed_answer();
+                }
+            } else if rl.is_key_pressed(KeyboardKey::KEY_SPACE) || rl.is_key_pressed(KeyboardKey::KEY_UP) {
+                game.flip();
+            }
+            // Only grade once the answer was already showing, so the key that
+            // picked a multiple-choice option is not also taken as a grade
+            if was_flipped && game.is_flipped {
+                for (key, grade) in GRADE_KEYS {
+                    if rl.is_key_pressed(key) {
+                        game.grade_card(grade);
+                    }
+                }
+                // ENTER accepts the grade suggested by the typed-answer check
+                let accept = !typing && rl.is_key_pressed(KeyboardKey::KEY_ENTER);
+                if let Some(grade) = game.suggested_grade().filter(|_| accept) {
+                    game.grade_card(grade);
+                }
+            }
+            // ENTER on the results screen starts a new exam or matching round
+            if (exam_finished || matching_finished) && rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                game.rebuild_due_cards();
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_TAB) {
+                game.set_mode(game.mode.next());
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) {
+                game.next_card();
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_LEFT) {
+                game.prev_card();
+            }
+            if !typing && rl.is_key_pressed(KeyboardKey::KEY_S) {
+                if game.shuffle_seed.is_some() {
+                    shuffle_seed = None;
+                    game.unshuffle();
+                } else {
+                    let seed = options.seed.unwrap_or_else(rand::random);
+                    eprintln!("Shuffling with seed {}", seed);
+                    shuffle_seed = Some(seed);
+                    game.shuffle(seed);
+                }
+            }
+            if game.session == SessionMode::Slideshow && rl.is_key_pressed(KeyboardKey::KEY_P) {
+                game.toggle_pause();
+            }
+            if !typing && rl.is_key_pressed(KeyboardKey::KEY_M) {
+                game.set_session(game.session.next());
+            }
+            if !typing && rl.is_key_pressed(KeyboardKey::KEY_R) {
+                game.set_direction(game.direction.next());
+            }
+            if !typing && rl.is_key_pressed(KeyboardKey::KEY_E) {
+                game.set_show_problems(true);
+            }
+            if !typing && rl.is_key_pressed(KeyboardKey::KEY_H) {
+                game.hint_shown = true;
+            }
+            // Switching decks from the all-deck queue goes to studying one deck
+            if !typing && (rl.is_key_pressed(KeyboardKey::KEY_A) || rl.is_key_pressed(KeyboardKey::KEY_D)) && decks.all_selected() {
+                decks.toggle_all_decks();
+            }
+            // With decks picked by X, A/D only moves the cursor X toggles and
+            // the session carries on with the same decks
+            let picked = decks.has_selection() && !decks.all_selected();
+            if !typing && rl.is_key_pressed(KeyboardKey::KEY_A) {
+                decks.prev_deck();
+                if !picked {
+                    update_decks(&decks, &mut game, shuffle_seed);
+                }
+            }
+            if !typing && rl.is_key_pressed(KeyboardKey::KEY_D) {
+                decks.next_deck();
+                if !picked {
+                    update_decks(&decks, &mut game, shuffle_seed);
+                }
+            }
+            // X adds or removes the current deck from a mixed session, SHIFT+X mixes all of them
+            if !typing && rl.is_key_pressed(KeyboardKey::KEY_X) {
+                if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT) {
+                    decks.toggle_all_decks();
+                } else {
+                    decks.toggle_current_deck();
+                }
+                update_decks(&decks, &mut game, shuffle_seed);
+            }
         }
 
         // Drawing
         let mut d = rl.begin_drawing(&thread);
         d.clear_background(Color::from_hex("2C3E50").unwrap());
 
-        // Draw Current Deck title.
-        let deck_title = format!("Deck: {}", decks.get_current_deck_name());
+        // Draw Current Deck title: the deck of the card on screen in a mixed session
+        let title_deck = game.current_card().map_or(&game.decks[0], |card| game.deck_of(card));
+        let mut deck_title = format!("Deck: {} ({})", title_deck.name, title_deck.scheduler.name());
+        if decks.is_mixed() {
+            deck_title = format!("{}  |  Mixed: {} decks", deck_title, game.decks.len());
+        }
+        // With decks picked by hand, A/D only moves the deck X toggles, so show it
+        if decks.has_selection() && !decks.all_selected() {
+            let (name, selected) = decks.current_deck();
+            let state = if selected { "in session" } else { "not in session" };
+            deck_title = format!("{}  |  X: {} ({})", deck_title, name, state);
+        }
         let y = 25.0;
 
-        draw_text_centered(&mut d, &custom_font, &deck_title, 400, 25, font_size, Color::WHITE);
+        let title_size = fit_font_size(&d, &custom_font, &deck_title, 780.0, font_size);
+        draw_text_centered(&mut d, &custom_font, &deck_title, 400, 25, title_size, Color::WHITE);
+
+        if game.show_problems {
+            draw_problems(&mut d, &custom_font, &game.problems);
+            draw_text_centered(&mut d, &custom_font, "Fix these in the deck files", 400, 470, font_size_smaller, signifier_color);
+            draw_text_centered(&mut d, &custom_font, "E: Close", 400, 550, font_size_smaller, signifier_color);
+            continue;
+        }
+
+        if game.screen == Screen::Summary {
+            draw_summary(&mut d, &custom_font, &game.stats, &game.cards);
+            draw_text_centered(&mut d, &custom_font, "SESSION COMPLETE", 400, 470, font_size_smaller, signifier_color);
+            let message = if game.due_cards.is_empty() {
+                "1: Restart  |  2: Study missed  |  3: Next deck"
+            } else {
+                "1: Restart  |  2: Study missed  |  3: Next deck  |  LEFT: Back to cards"
+            };
+            let message_size = fit_font_size(&d, &custom_font, message, 780.0, font_size_smaller);
+            draw_text_centered(&mut d, &custom_font, message, 400, 550, message_size, signifier_color);
+            continue;
+        }
 
         // Draw card background
         let card_rect = Rectangle::new(100.0, 100.0, 600.0, 350.0);
-        let card_color = if game.is_flipped {
+        let card_color = if game.is_flipped && game.choice.is_none() && !exam_finished {
             Color::from_hex("3498DB").unwrap()
         } else {
             Color::from_hex("ECF0F1").unwrap()
         };
-        d.draw_rectangle_rounded(card_rect, 0.05, 10, card_color);
+        if let Some(round) = &game.matching {
+            // The matching game lays out its own tiles instead of a card
+            draw_matching(&mut d, &custom_font, round);
+        } else {
+            d.draw_rectangle_rounded(card_rect, 0.05, 10, card_color);
 
-        // Draw card border
-        d.draw_rectangle_rounded_lines(card_rect, 0.05, 10, Color::from_hex("34495E").unwrap());
+            // Draw card border
+            d.draw_rectangle_rounded_lines(card_rect, 0.05, 10, Color::from_hex("34495E").unwrap());
+        }
 
         // Draw text
-        let text = game.get_current_text();
-        let wrapped_lines = wrap_text(text, 550, font_size as i32);
+        let waiting = game.next_learning_in().map(|wait| format!("Next learning card in {}", format_duration(wait)));
+        let text = if exam_finished || game.matching.is_some() {
+            // The results screen and the matching game draw their own text
+            ""
+        } else if game.due_cards.is_empty() && game.session == SessionMode::Cram {
+            "Every card answered correctly. Press M to finish cramming."
+        } else if game.due_cards.is_empty() && game.session == SessionMode::Drill {
+            "Drill finished! Press M to change session."
+        } else if let (true, Some(waiting)) = (game.due_cards.is_empty(), &waiting) {
+            waiting
+        } else if game.due_cards.is_empty() {
+            "No cards are due. Come back later!"
+        } else if game.choice.is_some() {
+            // The multiple-choice layout draws its own text
+            ""
+        } else {
+            game.get_current_text()
+        };
+        // Cloze fill-ins are highlighted on the back of the card
+        let highlights: &[(usize, usize)] = match game.current_card() {
+            Some(card) if game.is_flipped && game.choice.is_none() => &card.highlights,
+            _ => &[],
+        };
+        let wrapped_lines = wrap_highlighted(text, highlights, 550, font_size as i32);
         let line_height = (font_size + 5.0) as i32;
         let total_height = wrapped_lines.len() as i32 * line_height;
         let start_y = 275 - (total_height / 2);
@@ -278,26 +1930,178 @@ fn main() {
             Color::from_hex("2C3E50").unwrap()
         };
 
+        let highlight_color = Color::from_hex("F1C40F").unwrap();
+
         for (i, line) in wrapped_lines.iter().enumerate() {
             let y = start_y as f32 + (i as f32 * line_height as f32);
-            draw_text_centered(&mut d, &custom_font, &line, 400, y as i32, font_size, text_color);
+            let segments: Vec<(String, Color)> = line
+                .iter()
+                .map(|(piece, highlighted)| {
+                    (piece.clone(), if *highlighted { highlight_color } else { text_color })
+                })
+                .collect();
+            draw_segments_centered(&mut d, &custom_font, &segments, 400, y as i32, font_size);
         }
 
-        // Draw status indicator
-        let status_text = if game.is_flipped {
-            "ANSWER"
-        } else {
-            "QUESTION"
+        if let (Some(choice), Some(card)) = (&game.choice, game.current_card()) {
+            draw_multiple_choice(&mut d, &custom_font, card.front(), choice);
+        }
+
+        // The hint goes under the question once asked for, notes under the answer
+        let extra = match game.current_card() {
+            Some(_) if game.choice.is_some() || game.matching.is_some() || exam_finished => None,
+            Some(card) if !game.is_flipped && game.hint_shown => card.hint().map(|hint| format!("Hint: {}", hint)),
+            Some(card) if game.is_flipped && game.answer_check.is_none() => card.notes.clone(),
+            _ => None,
         };
-        draw_text_centered(&mut d, &custom_font, &status_text, 400, 470, font_size_smaller, signifier_color);
-        
+        if let Some(extra) = extra {
+            let color = if game.is_flipped { Color::from_hex("D6EAF8").unwrap() } else { signifier_color };
+            let size = fit_font_size(&d, &custom_font, &extra, 560.0, 24.0);
+            draw_text_centered(&mut d, &custom_font, &extra, 400, 415, size, color);
+        }
+
+        if let Some(fraction) = game.drill_time_left() {
+            draw_countdown_bar(&mut d, card_rect, fraction);
+        }
+
+        if let (true, Some(exam)) = (exam_finished, &game.exam) {
+            draw_exam_results(&mut d, &custom_font, exam, &game.cards);
+        }
+
+        // Show how the typed answer compared to the real one
+        if let (true, Some(check)) = (game.is_flipped, &game.answer_check) {
+            draw_answer_diff(&mut d, &custom_font, check, 395, 28.0);
+        }
+
+        // Draw status indicator, the answer box, or the grading options once the answer is shown
+        let slideshow = game.session == SessionMode::Slideshow;
+        if slideshow {
+            let status = if game.paused_at.is_some() {
+                "PAUSED"
+            } else if game.is_flipped {
+                "ANSWER"
+            } else {
+                "QUESTION"
+            };
+            draw_text_centered(&mut d, &custom_font, status, 400, 470, font_size_smaller, signifier_color);
+        } else if game.is_flipped {
+            draw_grade_options(&mut d, &custom_font, 470, font_size_smaller);
+        } else if typing {
+            draw_answer_input(&mut d, &custom_font, &game.typed_answer, 462, 30.0);
+        } else if choosing {
+            draw_text_centered(&mut d, &custom_font, "CHOOSE AN ANSWER", 400, 470, font_size_smaller, signifier_color);
+        } else if exam_finished {
+            draw_text_centered(&mut d, &custom_font, "RESULTS", 400, 470, font_size_smaller, signifier_color);
+        } else if game.matching.is_some() {
+            draw_text_centered(&mut d, &custom_font, "MATCH THE PAIRS", 400, 470, font_size_smaller, signifier_color);
+        } else {
+            draw_text_centered(&mut d, &custom_font, "QUESTION", 400, 470, font_size_smaller, signifier_color);
+        }
+
         // Draw card counter
-        let counter = format!("Card {} / {}", game.current_index + 1, game.cards.len());
-        draw_text_centered(&mut d, &custom_font, &counter, 400, 500, font_size_smaller, signifier_color);
+        let mut counter = if game.session == SessionMode::Cram {
+            format!(
+                "{}: {} left of {}  |  {} missed",
+                game.session.label(),
+                game.due_cards.len(),
+                game.studied_card_count(),
+                game.misses
+            )
+        } else if game.session == SessionMode::Drill {
+            format!(
+                "{}: {} left  |  {} in time, {} missed",
+                game.session.label(),
+                game.due_cards.len(),
+                game.hits,
+                game.misses
+            )
+        } else if let Some(round) = &game.matching {
+            format!(
+                "{}: {} / {} pairs  |  {} mistakes  |  {}",
+                game.session.label(),
+                round.matched_count(),
+                round.questions.len(),
+                round.mistakes,
+                format_duration(round.elapsed())
+            )
+        } else if let Some(exam) = &game.exam {
+            let mut counter = format!(
+                "{}: question {} / {}",
+                game.session.label(),
+                (exam.results.len() + 1).min(exam.cards.len()),
+                exam.cards.len()
+            );
+            if let (false, Some(remaining)) = (exam.is_finished(), exam.remaining()) {
+                counter = format!("{}  |  {} left", counter, format_duration(remaining));
+            }
+            counter
+        } else if game.session == SessionMode::Review {
+            let (due, new, learning) = game.remaining_by_stage();
+            format!("Remaining: {} due / {} new / {} learning", due, new, learning)
+        } else {
+            format!(
+                "Card {} / {}  ({} in deck)",
+                (game.current_index + 1).min(game.due_cards.len()),
+                game.due_cards.len(),
+                game.studied_card_count()
+            )
+        };
+        if let Some(label) = game.current_card().and_then(|card| stage_label(game.deck_of(card), card)) {
+            counter = format!("{}  |  {}", counter, label);
+        }
+        if game.shuffle_seed.is_some() {
+            counter = format!("{}  |  Shuffled", counter);
+        }
+        if game.direction != Direction::Forward {
+            counter = format!("{}  |  {}", counter, game.direction.label());
+        }
+        if game.mode != StudyMode::Flip {
+            counter = format!("{}  |  {}", counter, game.mode.label());
+        }
+        if game.mode == StudyMode::MultipleChoice {
+            counter = format!("{}  |  Score {} / {}", counter, game.quiz_correct, game.quiz_answered);
+        }
+        if let Some(card) = game.current_card().filter(|card| !card.tags.is_empty()) {
+            counter = format!("{}  |  Tags: {}", counter, card.tags.join(", "));
+        }
+        if !game.problems.is_empty() {
+            counter = format!("{}  |  {} deck problems (E)", counter, game.problems.len());
+        }
+        let counter_size = fit_font_size(&d, &custom_font, &counter, 780.0, font_size_smaller);
+        draw_text_centered(&mut d, &custom_font, &counter, 400, 510, counter_size, signifier_color);
 
         // Draw instructions
-        let message = "SPACE/UP: Flip  |  LEFT/RIGHT: Navigate | A/D Switch Decks";
-        draw_text_centered(&mut d, &custom_font, &message, 400, 550, font_size_smaller, signifier_color);
+        let message = if game.slideshow_ended() {
+            "End of deck  |  LEFT/RIGHT: Navigate  |  M: Change session"
+        } else if slideshow {
+            "P: Pause/Resume  |  LEFT/RIGHT: Navigate  |  M: Change session"
+        } else if game.timed_out {
+            "Time's up!  |  1-4: Grade  |  ENTER: Continue"
+        } else if game.due_cards.is_empty() && waiting.is_some() {
+            "RIGHT: End session now  |  M: Change session"
+        } else if exam_finished {
+            "ENTER: Retake exam  |  M: Change session  |  A/D: Switch Decks"
+        } else if matching_finished {
+            "ENTER: New round  |  M: Change session  |  A/D: Switch Decks"
+        } else if game.matching.is_some() {
+            "Click a question, then its answer  |  M: Change session"
+        } else if game.is_flipped && game.suggested_grade().is_some() {
+            "1-4: Grade  |  ENTER: Accept suggested grade"
+        } else if game.session == SessionMode::Exam && game.is_flipped {
+            "1: Wrong  |  2-4: Right"
+        } else if game.is_flipped {
+            "1-4: Grade  |  SPACE/UP: Flip back"
+        } else if typing {
+            "Type your answer  |  ENTER: Check  |  TAB: Change mode"
+        } else if choosing {
+            "1-4 or click: Choose  |  SPACE/UP: Reveal  |  TAB: Change mode"
+        } else if game.current_card().is_some_and(|card| card.hint().is_some()) && !game.hint_shown {
+            "SPACE/UP: Flip  |  H: Hint  |  LEFT/RIGHT: Navigate | A/D Switch Decks"
+        } else {
+            "SPACE/UP: Flip  |  LEFT/RIGHT: Navigate | A/D Switch Decks"
+        };
+        let message_size = fit_font_size(&d, &custom_font, message, 780.0, font_size_smaller);
+        draw_text_centered(&mut d, &custom_font, &message, 400, 550, message_size, signifier_color);
 
     }
 }
\ No newline at end of file
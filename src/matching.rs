@@ -0,0 +1,119 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+/// How long a wrong pair stays highlighted
+const WRONG_FLASH: Duration = Duration::from_millis(600);
+
+/// One clickable tile: a question or an answer
+pub struct Tile {
+    pub text: String,
+    pub pair: usize, // Index of the question/answer pair this tile came from
+    pub matched: bool,
+}
+
+/// A round of the matching game: questions in one column, their answers
+/// shuffled in another, matched up by clicking one of each
+pub struct MatchingRound {
+    pub questions: Vec<Tile>,
+    pub answers: Vec<Tile>,
+    pub selected: Option<(bool, usize)>, // Picked tile waiting for its partner: (is_answer, index)
+    pub mistakes: usize,
+    wrong: Option<(usize, usize, Instant)>, // Question and answer tiles of the last wrong pair
+    started: Instant,
+    finished: Option<Instant>,
+}
+
+impl MatchingRound {
+    /// Lays out a round from (question, answer) pairs, shuffling the answers
+    pub fn new<R: Rng>(pairs: &[(String, String)], rng: &mut R) -> Self {
+        let questions = pairs
+            .iter()
+            .enumerate()
+            .map(|(pair, (question, _))| Tile {
+                text: question.clone(),
+                pair,
+                matched: false,
+            })
+            .collect();
+        let mut answers: Vec<Tile> = pairs
+            .iter()
+            .enumerate()
+            .map(|(pair, (_, answer))| Tile {
+                text: answer.clone(),
+                pair,
+                matched: false,
+            })
+            .collect();
+        answers.shuffle(rng);
+
+        MatchingRound {
+            questions,
+            answers,
+            selected: None,
+            mistakes: 0,
+            wrong: None,
+            started: Instant::now(),
+            finished: None,
+        }
+    }
+
+    /// Handles a click on a tile. Picking a question and then an answer (or the
+    /// other way round) matches them if they belong together, otherwise it
+    /// counts as a mistake. Picking another tile from the same column just
+    /// moves the selection.
+    pub fn select(&mut self, is_answer: bool, index: usize) {
+        let column = if is_answer { &self.answers } else { &self.questions };
+        if self.is_finished() || column.get(index).is_none_or(|tile| tile.matched) {
+            return;
+        }
+
+        let (question, answer) = match self.selected {
+            Some((selected_is_answer, selected)) if selected_is_answer != is_answer => {
+                if is_answer { (selected, index) } else { (index, selected) }
+            }
+            _ => {
+                self.selected = Some((is_answer, index));
+                return;
+            }
+        };
+        self.selected = None;
+
+        // Answers with the same text are interchangeable
+        let expected = self.answers.iter().find(|tile| tile.pair == self.questions[question].pair);
+        let correct = expected.is_some_and(|tile| tile.text == self.answers[answer].text);
+        if correct {
+            self.questions[question].matched = true;
+            self.answers[answer].matched = true;
+            if self.questions.iter().all(|tile| tile.matched) {
+                self.finished = Some(Instant::now());
+            }
+        } else {
+            self.mistakes += 1;
+            self.wrong = Some((question, answer, Instant::now()));
+        }
+    }
+
+    /// Returns true if the tile was part of a wrong pair picked a moment ago
+    pub fn is_flashing_wrong(&self, is_answer: bool, index: usize) -> bool {
+        match self.wrong {
+            Some((question, answer, when)) if when.elapsed() < WRONG_FLASH => {
+                if is_answer { answer == index } else { question == index }
+            }
+            _ => false,
+        }
+    }
+
+    pub fn matched_count(&self) -> usize {
+        self.questions.iter().filter(|tile| tile.matched).count()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    /// Time spent so far, or in total once every pair is matched
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now) - self.started
+    }
+}
@@ -0,0 +1,241 @@
+/// Which side of each card is shown first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward, // Question first
+    Reverse, // Answer first
+    Both,    // Every card in both directions, tracked separately
+}
+
+impl Direction {
+    /// Parses a direction name as used on the command line
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "forward" => Some(Direction::Forward),
+            "reverse" => Some(Direction::Reverse),
+            "both" => Some(Direction::Both),
+            _ => None,
+        }
+    }
+
+    /// Cycles Forward -> Reverse -> Both -> Forward
+    pub fn next(self) -> Self {
+        match self {
+            Direction::Forward => Direction::Reverse,
+            Direction::Reverse => Direction::Both,
+            Direction::Both => Direction::Forward,
+        }
+    }
+
+    /// Returns true if cards facing this way (`reversed` or not) are studied
+    pub fn includes(self, reversed: bool) -> bool {
+        match self {
+            Direction::Forward => !reversed,
+            Direction::Reverse => reversed,
+            Direction::Both => true,
+        }
+    }
+
+    /// Name shown in the UI
+    pub fn label(self) -> &'static str {
+        match self {
+            Direction::Forward => "Forward",
+            Direction::Reverse => "Reverse",
+            Direction::Both => "Both ways",
+        }
+    }
+}
+
+/// How the user answers each card
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StudyMode {
+    Flip,           // Think of the answer, flip, then grade yourself
+    Typed,          // Type the answer and have it checked
+    MultipleChoice, // Pick the answer from options drawn from the deck
+}
+
+impl StudyMode {
+    /// Every mode, in the order TAB cycles through them
+    const ALL: [StudyMode; 3] = [StudyMode::Flip, StudyMode::Typed, StudyMode::MultipleChoice];
+
+    /// Parses a mode name as used on the command line
+    pub fn parse(name: &str) -> Option<Self> {
+        StudyMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// Command line name of the mode
+    pub fn name(self) -> &'static str {
+        match self {
+            StudyMode::Flip => "flip",
+            StudyMode::Typed => "typed",
+            StudyMode::MultipleChoice => "choice",
+        }
+    }
+
+    /// Name shown in the UI
+    pub fn label(self) -> &'static str {
+        match self {
+            StudyMode::Flip => "Flip",
+            StudyMode::Typed => "Typed",
+            StudyMode::MultipleChoice => "Multiple choice",
+        }
+    }
+
+    /// Cycles through the modes in order
+    pub fn next(self) -> Self {
+        let index = StudyMode::ALL.iter().position(|&mode| mode == self).unwrap_or(0);
+        StudyMode::ALL[(index + 1) % StudyMode::ALL.len()]
+    }
+}
+
+/// What a study session is made of and when it ends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionMode {
+    Review,    // Cards that are due, graded into the schedule
+    Cram,      // Every card, repeating misses until all are right; the schedule is untouched
+    Exam,      // A fixed number of random cards, scored, with no going back
+    Drill,     // Every card against a countdown; running out of time is a miss
+    Match,     // Pair up questions with their shuffled answers against the clock
+    Slideshow, // Hands-free: cards flip and advance on their own
+}
+
+impl SessionMode {
+    /// Every session mode, in the order M cycles through them
+    const ALL: [SessionMode; 6] = [
+        SessionMode::Review,
+        SessionMode::Cram,
+        SessionMode::Exam,
+        SessionMode::Drill,
+        SessionMode::Match,
+        SessionMode::Slideshow,
+    ];
+
+    /// Parses a session name as used on the command line
+    pub fn parse(name: &str) -> Option<Self> {
+        SessionMode::ALL.into_iter().find(|session| session.name() == name)
+    }
+
+    /// Command line name of the session mode
+    pub fn name(self) -> &'static str {
+        match self {
+            SessionMode::Review => "review",
+            SessionMode::Cram => "cram",
+            SessionMode::Exam => "exam",
+            SessionMode::Drill => "drill",
+            SessionMode::Match => "match",
+            SessionMode::Slideshow => "slideshow",
+        }
+    }
+
+    /// Name shown in the UI
+    pub fn label(self) -> &'static str {
+        match self {
+            SessionMode::Review => "Review",
+            SessionMode::Cram => "Cram",
+            SessionMode::Exam => "Exam",
+            SessionMode::Drill => "Drill",
+            SessionMode::Match => "Matching",
+            SessionMode::Slideshow => "Slideshow",
+        }
+    }
+
+    /// Cycles through the session modes in order
+    pub fn next(self) -> Self {
+        let index = SessionMode::ALL.iter().position(|&session| session == self).unwrap_or(0);
+        SessionMode::ALL[(index + 1) % SessionMode::ALL.len()]
+    }
+}
+
+/// Command line options
+pub struct Options {
+    pub shuffle: bool,     // Start every deck in shuffled order
+    pub seed: Option<u64>, // Fixed shuffle seed so a session can be replayed
+    pub direction: Direction,
+    pub mode: StudyMode,
+    pub session: SessionMode,
+    pub decks: Vec<String>, // Decks to study together, by name; empty for a single deck
+    pub import_anki: Option<String>, // Anki plain-text export to turn into a deck before starting
+}
+
+pub const USAGE: &str = "Usage: flashcards-with-raylib [OPTIONS]
+
+Options:
+  --shuffle            Shuffle the cards of each deck
+  --seed <N>           Shuffle with a fixed seed (implies --shuffle)
+  --direction <DIR>    Study forward, reverse (answer first) or both
+  --mode <MODE>        Answer by flipping (flip), typing (typed) or
+                       picking from options (choice)
+  --session <SESSION>  Review due cards (review), cram every card
+                       until all are answered correctly (cram),
+                       take a scored exam (exam), answer every
+                       card against the clock (drill), pair up
+                       questions and answers (match) or watch the
+                       cards play by themselves (slideshow)
+  --decks <LIST>       Study several decks together, e.g. spanish,french,
+                       or all of them with --decks all
+  --import-anki <FILE> Convert an Anki \"Notes in Plain Text\" export
+                       into a deck in flashcard_decks/, then start
+  -h, --help           Show this message";
+
+impl Options {
+    /// Parses the process arguments, returning an error message for bad input
+    pub fn from_args() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            shuffle: false,
+            seed: None,
+            direction: Direction::Forward,
+            mode: StudyMode::Flip,
+            session: SessionMode::Review,
+            decks: Vec::new(),
+            import_anki: None,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--shuffle" => options.shuffle = true,
+                "--seed" => {
+                    let value = args.next().ok_or("--seed needs a number")?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("invalid seed '{}'", value))?;
+                    options.seed = Some(seed);
+                    options.shuffle = true;
+                }
+                "--direction" => {
+                    let value = args.next().ok_or("--direction needs forward, reverse or both")?;
+                    options.direction = Direction::parse(&value)
+                        .ok_or_else(|| format!("invalid direction '{}'", value))?;
+                }
+                "--mode" => {
+                    let value = args.next().ok_or("--mode needs a mode name")?;
+                    options.mode = StudyMode::parse(&value)
+                        .ok_or_else(|| format!("invalid mode '{}'", value))?;
+                }
+                "--session" => {
+                    let value = args.next().ok_or("--session needs review, cram, exam, drill, match or slideshow")?;
+                    options.session = SessionMode::parse(&value)
+                        .ok_or_else(|| format!("invalid session '{}'", value))?;
+                }
+                "--decks" => {
+                    let value = args.next().ok_or("--decks needs deck names or all")?;
+                    options.decks = value
+                        .split(',')
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                        .collect();
+                }
+                "--import-anki" => {
+                    let value = args.next().ok_or("--import-anki needs the path of an Anki export")?;
+                    options.import_anki = Some(value);
+                }
+                "-h" | "--help" => return Err(String::new()),
+                other => return Err(format!("unknown option '{}'", other)),
+            }
+        }
+
+        Ok(options)
+    }
+}
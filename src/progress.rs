@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::scheduler::{Grade, ReviewLog, ReviewState};
+use crate::utils::levenshtein;
+
+/// First line of every progress file, bumped if the format ever changes
+const HEADER: &str = "# flashcards progress v1";
+
+/// Returns a stable ID for a card without an explicit one: a hash of its question.
+///
+/// Uses 64-bit FNV-1a rather than `DefaultHasher`, whose output may change
+/// between Rust releases and would orphan every saved entry.
+pub fn content_id(question: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in question.trim().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Saved state for one card, along with the question it was saved for
+struct Entry {
+    question: String,
+    state: ReviewState,
+}
+
+/// Outcome of matching saved progress against the cards in a deck
+#[derive(Default)]
+pub struct Reconciliation {
+    pub remapped: Vec<(String, String)>, // (old question, new question) for edited or re-IDed cards
+    pub orphaned: Vec<String>,           // Questions of saved entries with no matching card
+}
+
+/// Saved review state for every card in one deck.
+///
+/// Stored next to the deck as `<deck>.progress`, one tab-separated line per
+/// card: `id, question, due, interval, ease, repetitions, leitner box,
+/// learning step, history`.
+pub struct ProgressStore {
+    path: PathBuf,
+    entries: HashMap<String, Entry>, // Keyed by card ID
+}
+
+impl ProgressStore {
+    /// Loads the progress saved for the deck at `deck_path`, if any
+    pub fn load(deck_path: &str) -> Self {
+        let path = Path::new(deck_path).with_extension("progress");
+        let mut entries = HashMap::new();
+
+        if let Ok(contents) = fs::read_to_string(&path) {
+            for (line_number, line) in contents.lines().enumerate() {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                match parse_entry(line) {
+                    Some((id, entry)) => {
+                        entries.insert(id, entry);
+                    }
+                    None => eprintln!(
+                        "Warning: {}:{}: ignoring malformed progress entry",
+                        path.display(),
+                        line_number + 1
+                    ),
+                }
+            }
+        }
+
+        ProgressStore { path, entries }
+    }
+
+    /// Returns the saved state for a card, if it has been studied before
+    pub fn get(&self, id: &str) -> Option<&ReviewState> {
+        self.entries.get(id).map(|entry| &entry.state)
+    }
+
+    /// Records the latest state for a card (call `save` to persist it)
+    pub fn update(&mut self, id: &str, question: &str, state: &ReviewState) {
+        let entry = Entry {
+            question: question.to_string(),
+            state: state.clone(),
+        };
+        self.entries.insert(id.to_string(), entry);
+    }
+
+    /// Maps saved entries onto the deck's current cards, given as `(id, question)`.
+    ///
+    /// Entries whose ID no longer exists are moved to an unmatched card with the
+    /// same question (its ID changed) or a very similar one (a typo was fixed).
+    /// Anything left over is reported as orphaned but kept on disk, so progress
+    /// comes back if the card is restored.
+    pub fn reconcile(&mut self, cards: &[(&str, &str)]) -> Reconciliation {
+        let mut report = Reconciliation::default();
+
+        let mut unmatched_cards: Vec<(&str, &str)> = cards
+            .iter()
+            .filter(|(id, _)| !self.entries.contains_key(*id))
+            .copied()
+            .collect();
+        let card_ids: Vec<&str> = cards.iter().map(|(id, _)| *id).collect();
+        let mut orphan_ids: Vec<String> = self
+            .entries
+            .keys()
+            .filter(|id| !card_ids.contains(&id.as_str()))
+            .cloned()
+            .collect();
+        orphan_ids.sort();
+
+        for orphan_id in orphan_ids {
+            let question = self.entries[&orphan_id].question.clone();
+
+            // Prefer an exact question match, then the closest small edit
+            let best = unmatched_cards
+                .iter()
+                .enumerate()
+                .map(|(i, (_, card_question))| (i, levenshtein(&question, card_question)))
+                .filter(|&(_, distance)| distance <= max_edit_distance(&question))
+                .min_by_key(|&(_, distance)| distance);
+
+            match best {
+                Some((i, _)) => {
+                    let (new_id, new_question) = unmatched_cards.remove(i);
+                    let entry = self.entries.remove(&orphan_id).unwrap();
+                    self.entries.insert(new_id.to_string(), entry);
+                    report.remapped.push((question, new_question.to_string()));
+                }
+                None => report.orphaned.push(question),
+            }
+        }
+
+        report
+    }
+
+    /// Writes the store to disk.
+    ///
+    /// The data goes to a temporary file first and is then renamed over the
+    /// old one, so a crash mid-write never leaves a half-written file behind.
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        let tmp_path = self.path.with_extension("progress.tmp");
+
+        // Sort for stable, diff-friendly output
+        let mut keys: Vec<&String> = self.entries.keys().collect();
+        keys.sort();
+
+        let mut file = File::create(&tmp_path)?;
+        writeln!(file, "{}", HEADER)?;
+        for key in keys {
+            writeln!(file, "{}", format_entry(key, &self.entries[key]))?;
+        }
+        file.sync_all()?;
+
+        fs::rename(&tmp_path, &self.path)
+    }
+}
+
+/// Edits allowed when matching a saved question to a changed card: about one in ten characters
+fn max_edit_distance(question: &str) -> usize {
+    question.chars().count() / 10
+}
+
+/// Serializes one card's state as a tab-separated line
+fn format_entry(id: &str, entry: &Entry) -> String {
+    let state = &entry.state;
+    let history = state
+        .history
+        .iter()
+        .map(|review| format!("{}:{}", review.time, review.grade.rating()))
+        .collect::<Vec<_>>()
+        .join(";");
+    let learning_step = match state.learning_step {
+        Some(step) => step.to_string(),
+        None => "-".to_string(),
+    };
+
+    format!(
+        "{}\t{}\t{}\t{}\t{:.4}\t{}\t{}\t{}\t{}",
+        escape(id),
+        escape(&entry.question),
+        state.due,
+        state.interval,
+        state.ease_factor,
+        state.repetitions,
+        state.leitner_box,
+        learning_step,
+        history
+    )
+}
+
+/// Parses a line written by `format_entry`
+fn parse_entry(line: &str) -> Option<(String, Entry)> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [id, question, due, interval, ease, repetitions, leitner_box, learning_step, reviews] = fields[..] else {
+        return None;
+    };
+
+    let learning_step = match learning_step {
+        "-" => None,
+        step => Some(step.parse().ok()?),
+    };
+
+    let mut history = Vec::new();
+    for review in reviews.split(';').filter(|r| !r.is_empty()) {
+        let (time, rating) = review.split_once(':')?;
+        history.push(ReviewLog {
+            time: time.parse().ok()?,
+            grade: Grade::from_rating(rating.parse().ok()?)?,
+        });
+    }
+
+    let state = ReviewState {
+        due: due.parse().ok()?,
+        interval: interval.parse().ok()?,
+        ease_factor: ease.parse().ok()?,
+        repetitions: repetitions.parse().ok()?,
+        leitner_box: leitner_box.parse().ok()?,
+        learning_step,
+        history,
+    };
+
+    let entry = Entry {
+        question: unescape(question),
+        state,
+    };
+
+    Some((unescape(id), entry))
+}
+
+/// Escapes characters that would break the line-based format
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Reverses `escape`
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('t') => result.push('\t'),
+                Some('n') => result.push('\n'),
+                Some('r') => result.push('\r'),
+                Some(other) => result.push(other),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(entries: &[(&str, &str)]) -> ProgressStore {
+        let mut store = ProgressStore {
+            path: PathBuf::new(),
+            entries: HashMap::new(),
+        };
+        for (id, question) in entries {
+            store.update(id, question, &ReviewState::default());
+        }
+        store
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        for text in ["plain", "tab\there", "two\nlines", "crlf\r\n", "back\\slash", "\\t is not a tab", "ends in \\"] {
+            assert_eq!(unescape(&escape(text)), text);
+            assert!(!escape(text).contains(['\t', '\n', '\r']), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn entries_round_trip_through_a_line() {
+        let mut state = ReviewState {
+            due: 1_700_000_000,
+            interval: 6,
+            ease_factor: 2.36,
+            repetitions: 2,
+            leitner_box: 3,
+            learning_step: Some(1),
+            history: Vec::new(),
+        };
+        state.history.push(ReviewLog { time: 1_699_000_000, grade: Grade::Good });
+        state.history.push(ReviewLog { time: 1_699_500_000, grade: Grade::Again });
+        let entry = Entry {
+            question: "States of matter?\tSolid\nLiquid".to_string(),
+            state,
+        };
+
+        let line = format_entry("id\twith\\tab", &entry);
+        assert!(!line.contains('\n'));
+        let Some((id, parsed)) = parse_entry(&line) else {
+            panic!("could not parse {:?}", line);
+        };
+        assert_eq!(id, "id\twith\\tab");
+        assert_eq!(parsed.question, entry.question);
+        assert_eq!(parsed.state.due, 1_700_000_000);
+        assert_eq!(parsed.state.interval, 6);
+        assert_eq!(parsed.state.ease_factor, 2.36);
+        assert_eq!((parsed.state.repetitions, parsed.state.leitner_box), (2, 3));
+        assert_eq!(parsed.state.learning_step, Some(1));
+        let history: Vec<(u64, Grade)> = parsed.state.history.iter().map(|r| (r.time, r.grade)).collect();
+        assert_eq!(history, [(1_699_000_000, Grade::Good), (1_699_500_000, Grade::Again)]);
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        assert!(parse_entry("").is_none());
+        assert!(parse_entry("id\tquestion\tnot a number\t1\t2.5\t0\t1\t-\t").is_none());
+        assert!(parse_entry("id\tquestion\t0\t1\t2.5\t0\t1\t-\t5:9").is_none());
+    }
+
+    #[test]
+    fn reconcile_follows_changed_ids_and_fixed_typos() {
+        let mut progress = store(&[
+            ("kept", "What is the capital of France?"),
+            ("old-id", "What is the symbol for gold?"),
+            ("typo", "What is the capitol of Germany?"),
+            ("gone", "Which planet is largest?"),
+        ]);
+        let cards = [
+            ("kept", "What is the capital of France?"),
+            ("new-id", "What is the symbol for gold?"),
+            ("fixed", "What is the capital of Germany?"),
+            ("fresh", "What is the speed of light?"),
+        ];
+
+        let report = progress.reconcile(&cards);
+
+        for id in ["kept", "new-id", "fixed"] {
+            assert!(progress.get(id).is_some(), "{} has no progress", id);
+        }
+        assert!(progress.get("fresh").is_none());
+        let mut remapped = report.remapped.clone();
+        remapped.sort();
+        assert_eq!(
+            remapped,
+            [
+                ("What is the capitol of Germany?".to_string(), "What is the capital of Germany?".to_string()),
+                ("What is the symbol for gold?".to_string(), "What is the symbol for gold?".to_string()),
+            ]
+        );
+        // Unmatched progress is reported but kept for when the card comes back
+        assert_eq!(report.orphaned, ["Which planet is largest?"]);
+        assert!(progress.get("gone").is_some());
+    }
+
+    #[test]
+    fn reconcile_prefers_the_closest_question() {
+        let mut progress = store(&[("old", "Name the largest ocean on Earth")]);
+        let report = progress.reconcile(&[("a", "Name the largest ocean on Mars"), ("b", "Name the largest ocean on Earth.")]);
+        assert_eq!(report.remapped.len(), 1);
+        assert!(progress.get("b").is_some());
+        assert!(progress.get("a").is_none());
+    }
+
+    #[test]
+    fn reconcile_leaves_rewritten_questions_orphaned() {
+        let mut progress = store(&[("old", "Capital of Spain?")]);
+        let report = progress.reconcile(&[("new", "Which city is the seat of the Spanish government?")]);
+        assert!(report.remapped.is_empty());
+        assert_eq!(report.orphaned, ["Capital of Spain?"]);
+        assert!(progress.get("new").is_none());
+    }
+}
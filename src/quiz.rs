@@ -0,0 +1,60 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Number of options offered for each question, including the correct one
+pub const OPTION_COUNT: usize = 4;
+
+/// Candidates considered for each distractor slot, closest in length first
+const DISTRACTOR_POOL: usize = 6;
+
+/// A multiple-choice question built from one card
+pub struct MultipleChoice {
+    pub options: Vec<String>,
+    pub correct: usize,        // Index of the right answer in `options`
+    pub chosen: Option<usize>, // Index picked by the user, once answered
+}
+
+impl MultipleChoice {
+    /// Builds a question from the correct answer and the other answers in the deck.
+    ///
+    /// Distractors are drawn at random from the answers closest in length to
+    /// the correct one, so the right option does not stand out by its size.
+    pub fn new<R: Rng>(answer: &str, other_answers: &[&str], rng: &mut R) -> Self {
+        // Unique answers that differ from the correct one
+        let mut candidates: Vec<&str> = Vec::new();
+        for &other in other_answers {
+            let other = other.trim();
+            let duplicate = candidates.iter().any(|c| c.eq_ignore_ascii_case(other));
+            if !other.is_empty() && !other.eq_ignore_ascii_case(answer.trim()) && !duplicate {
+                candidates.push(other);
+            }
+        }
+
+        // Shuffle first so ties in length are broken randomly
+        candidates.shuffle(rng);
+        let length = answer.chars().count() as i64;
+        candidates.sort_by_key(|c| (c.chars().count() as i64 - length).abs());
+        candidates.truncate(DISTRACTOR_POOL.max(OPTION_COUNT - 1));
+        candidates.shuffle(rng);
+
+        let mut options: Vec<String> = candidates
+            .into_iter()
+            .take(OPTION_COUNT - 1)
+            .map(|c| c.to_string())
+            .collect();
+        options.push(answer.to_string());
+        options.shuffle(rng);
+
+        let correct = options.iter().position(|o| o == answer).unwrap_or(0);
+        MultipleChoice {
+            options,
+            correct,
+            chosen: None,
+        }
+    }
+
+    /// Returns true once an option has been picked and it was the right one
+    pub fn is_correct(&self) -> bool {
+        self.chosen == Some(self.correct)
+    }
+}
@@ -0,0 +1,591 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{Local, NaiveDate, TimeZone, Timelike};
+
+use crate::settings::DeckSettings;
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Returns when the study day containing `time` began and when it ends.
+/// Days roll over at `rollover_hour` o'clock local time, so around a
+/// daylight saving change a day is an hour shorter or longer.
+pub fn study_day(time: u64, rollover_hour: u64) -> (u64, u64) {
+    study_day_in(&Local, time, rollover_hour)
+}
+
+fn study_day_in<Tz: TimeZone>(tz: &Tz, time: u64, rollover_hour: u64) -> (u64, u64) {
+    let hour = (rollover_hour % 24) as u32;
+    let Some(local) = tz.timestamp_opt(time as i64, 0).single() else {
+        return (time, time + SECONDS_PER_DAY);
+    };
+
+    let mut date = local.date_naive();
+    if local.hour() < hour {
+        date = date.pred_opt().unwrap_or(date);
+    }
+    let start = rollover_on(tz, date, hour).unwrap_or(time);
+    let end = date
+        .succ_opt()
+        .and_then(|next| rollover_on(tz, next, hour))
+        .unwrap_or(start + SECONDS_PER_DAY);
+    (start, end)
+}
+
+/// When `hour` o'clock comes round on `date`. If daylight saving skips that
+/// hour, the day starts an hour later instead.
+fn rollover_on<Tz: TimeZone>(tz: &Tz, date: NaiveDate, hour: u32) -> Option<u64> {
+    [hour, hour + 1]
+        .into_iter()
+        .find_map(|h| tz.from_local_datetime(&date.and_hms_opt(h, 0, 0)?).earliest())
+        .map(|start| start.timestamp().max(0) as u64)
+}
+
+/// Returns the current time in seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// How well the user remembered the answer to a card
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    /// All grades in the order of their keyboard shortcuts (1-4)
+    pub const ALL: [Grade; 4] = [Grade::Again, Grade::Hard, Grade::Good, Grade::Easy];
+
+    /// Name shown on the grading buttons
+    pub fn label(self) -> &'static str {
+        match self {
+            Grade::Again => "Again",
+            Grade::Hard => "Hard",
+            Grade::Good => "Good",
+            Grade::Easy => "Easy",
+        }
+    }
+
+    /// Maps the grade onto the 0-5 quality scale used by SM-2
+    pub fn quality(self) -> u8 {
+        match self {
+            Grade::Again => 1,
+            Grade::Hard => 3,
+            Grade::Good => 4,
+            Grade::Easy => 5,
+        }
+    }
+
+    /// Returns true if the card was remembered at all
+    pub fn is_pass(self) -> bool {
+        self != Grade::Again
+    }
+
+    /// Maps the grade onto the 1-4 rating scale used by FSRS
+    pub fn rating(self) -> usize {
+        match self {
+            Grade::Again => 1,
+            Grade::Hard => 2,
+            Grade::Good => 3,
+            Grade::Easy => 4,
+        }
+    }
+
+    /// Inverse of `rating`
+    pub fn from_rating(rating: usize) -> Option<Grade> {
+        Grade::ALL.get(rating.checked_sub(1)?).copied()
+    }
+}
+
+/// A single past review of a card
+#[derive(Clone, Copy, Debug)]
+pub struct ReviewLog {
+    pub time: u64, // Unix timestamp of the review
+    pub grade: Grade,
+}
+
+/// Per-card scheduling state shared by all schedulers
+#[derive(Clone, Debug)]
+pub struct ReviewState {
+    pub ease_factor: f64,           // SM-2 multiplier applied to the interval after each success
+    pub interval: u32,              // Days until the next review
+    pub repetitions: u32,           // Successful reviews in a row
+    pub due: u64,                   // Unix timestamp when the card should next be shown
+    pub leitner_box: u32,           // Leitner box the card currently lives in (1-based)
+    pub learning_step: Option<u32>, // Learning step the card is on, None once it has graduated
+    pub history: Vec<ReviewLog>,
+}
+
+impl Default for ReviewState {
+    /// A new card starts with the standard 2.5 ease and is due immediately
+    fn default() -> Self {
+        ReviewState {
+            ease_factor: 2.5,
+            interval: 0,
+            repetitions: 0,
+            due: 0,
+            leitner_box: 1,
+            learning_step: None,
+            history: Vec::new(),
+        }
+    }
+}
+
+impl ReviewState {
+    /// Returns true if the card should be presented at the given time
+    pub fn is_due(&self, now: u64) -> bool {
+        self.due <= now
+    }
+
+    /// Where the card is in its life: new, learning or on the day-level schedule
+    pub fn stage(&self) -> Stage {
+        if self.learning_step.is_some() {
+            Stage::Learning
+        } else if self.history.is_empty() && self.due == 0 {
+            Stage::New
+        } else {
+            Stage::Review
+        }
+    }
+
+    /// Logs a review and picks the next due date: through the learning steps
+    /// for new and learning cards, otherwise with the scheduler
+    pub fn record(&mut self, scheduler: &dyn Scheduler, steps: &LearningSteps, grade: Grade, now: u64) {
+        let stage = self.stage();
+        self.history.push(ReviewLog { time: now, grade });
+
+        if stage == Stage::Review || steps.is_empty() {
+            self.learning_step = None;
+            scheduler.schedule(self, grade, now);
+        } else {
+            steps.schedule(self, scheduler, grade, now);
+        }
+    }
+}
+
+/// Where a card is in its life
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    New,      // Never studied
+    Learning, // Going through the short learning steps
+    Review,   // Graduated to day-level scheduling
+}
+
+impl Stage {
+    /// Name shown in the UI
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::New => "New",
+            Stage::Learning => "Learning",
+            Stage::Review => "Review",
+        }
+    }
+}
+
+/// Steps used when `learning_steps` is not set: 1 minute, then 10 minutes
+const DEFAULT_LEARNING_STEPS: [f64; 2] = [1.0, 10.0];
+
+/// Short delays a new card goes through within a session before it
+/// graduates to the scheduler
+pub struct LearningSteps {
+    steps: Vec<u64>, // Delay of each step, in seconds
+}
+
+impl LearningSteps {
+    /// Reads the `learning_steps` setting: minutes per step, e.g. `1, 10`.
+    /// An empty value turns learning steps off.
+    pub fn from_settings(settings: &DeckSettings) -> Self {
+        let minutes = match settings.get_str("learning_steps") {
+            Some(value) => value
+                .split(',')
+                .map(|step| step.trim())
+                .filter(|step| !step.is_empty())
+                .map(|step| step.parse::<f64>().ok().filter(|m| *m > 0.0))
+                .collect::<Option<Vec<_>>>()
+                .unwrap_or_else(|| {
+                    eprintln!("Warning: learning_steps expects a list of minutes like '1, 10'");
+                    DEFAULT_LEARNING_STEPS.to_vec()
+                }),
+            None => DEFAULT_LEARNING_STEPS.to_vec(),
+        };
+
+        LearningSteps {
+            steps: minutes.into_iter().map(|m| (m * 60.0).round() as u64).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Number of steps, for display
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Moves a new or learning card through the steps: Again goes back to the
+    /// first step, Hard repeats the current one and Good moves to the next.
+    /// Good on the last step, or Easy at any point, graduates the card and
+    /// hands it to the scheduler.
+    fn schedule(&self, state: &mut ReviewState, scheduler: &dyn Scheduler, grade: Grade, now: u64) {
+        let current = state.learning_step.unwrap_or(0) as usize;
+        let next = match grade {
+            Grade::Again => Some(0),
+            Grade::Hard => Some(current.min(self.steps.len() - 1)),
+            Grade::Good => Some(current + 1).filter(|&step| step < self.steps.len()),
+            Grade::Easy => None,
+        };
+
+        match next {
+            Some(step) => {
+                state.learning_step = Some(step as u32);
+                state.due = now + self.steps[step];
+            }
+            None => {
+                state.learning_step = None;
+                scheduler.schedule(state, grade, now);
+            }
+        }
+    }
+}
+
+/// A spaced repetition algorithm that decides when a card is next due
+pub trait Scheduler {
+    /// Short name shown in the UI
+    fn name(&self) -> &str;
+
+    /// Updates `state` after a review; `state.history` already ends with this review
+    fn schedule(&self, state: &mut ReviewState, grade: Grade, now: u64);
+
+    /// Sort key for due cards; lower values are studied first
+    fn queue_order(&self, _state: &ReviewState) -> u32 {
+        0
+    }
+
+    /// Optional per-card detail shown next to the card counter
+    fn card_label(&self, _state: &ReviewState) -> Option<String> {
+        None
+    }
+}
+
+/// Builds the scheduler selected by the deck's `scheduler` setting
+pub fn from_settings(settings: &DeckSettings) -> Box<dyn Scheduler> {
+    match settings.get_str("scheduler").unwrap_or("sm2") {
+        "fsrs" => Box::new(Fsrs::new(settings.get_f64("target_retention", 0.9))),
+        "leitner" => Box::new(Leitner::from_settings(settings)),
+        "sm2" => Box::new(Sm2),
+        other => {
+            eprintln!("Warning: unknown scheduler '{}', using sm2", other);
+            Box::new(Sm2)
+        }
+    }
+}
+
+/// The classic SuperMemo 2 algorithm
+pub struct Sm2;
+
+impl Scheduler for Sm2 {
+    fn name(&self) -> &str {
+        "SM-2"
+    }
+
+    fn schedule(&self, state: &mut ReviewState, grade: Grade, now: u64) {
+        let quality = grade.quality() as f64;
+
+        if quality >= 3.0 {
+            // Successful recall: grow the interval
+            state.interval = match state.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (state.interval as f64 * state.ease_factor).round() as u32,
+            };
+            state.repetitions += 1;
+        } else {
+            // Lapse: start the card over but keep its ease history
+            state.repetitions = 0;
+            state.interval = 1;
+        }
+
+        // Standard SM-2 ease adjustment, never dropping below 1.3
+        let penalty = 5.0 - quality;
+        state.ease_factor += 0.1 - penalty * (0.08 + penalty * 0.02);
+        if state.ease_factor < 1.3 {
+            state.ease_factor = 1.3;
+        }
+
+        state.due = now + state.interval as u64 * SECONDS_PER_DAY;
+    }
+}
+
+// FSRS-4.5 forgetting curve constants
+const DECAY: f64 = -0.5;
+const FACTOR: f64 = 19.0 / 81.0;
+
+// FSRS-4.5 default parameters
+const FSRS_WEIGHTS: [f64; 17] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461,
+    2.1072, 0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+
+/// Free Spaced Repetition Scheduler: models stability, difficulty and retrievability
+pub struct Fsrs {
+    target_retention: f64, // Probability of recall we aim for when the card comes due
+    w: [f64; 17],
+}
+
+impl Fsrs {
+    pub fn new(target_retention: f64) -> Self {
+        Fsrs {
+            target_retention: target_retention.clamp(0.7, 0.99),
+            w: FSRS_WEIGHTS,
+        }
+    }
+
+    /// Probability of recalling a card `elapsed_days` after its last review
+    fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+        (1.0 + FACTOR * elapsed_days / stability).powf(DECAY)
+    }
+
+    fn initial_difficulty(&self, grade: Grade) -> f64 {
+        (self.w[4] - (grade.rating() as f64 - 3.0) * self.w[5]).clamp(1.0, 10.0)
+    }
+
+    fn next_difficulty(&self, difficulty: f64, grade: Grade) -> f64 {
+        let next = difficulty - self.w[6] * (grade.rating() as f64 - 3.0);
+        // Mean reversion towards the difficulty of a "Good" first review
+        let reverted = self.w[7] * self.initial_difficulty(Grade::Good) + (1.0 - self.w[7]) * next;
+        reverted.clamp(1.0, 10.0)
+    }
+
+    fn next_recall_stability(&self, difficulty: f64, stability: f64, r: f64, grade: Grade) -> f64 {
+        let hard_penalty = if grade == Grade::Hard { self.w[15] } else { 1.0 };
+        let easy_bonus = if grade == Grade::Easy { self.w[16] } else { 1.0 };
+
+        stability
+            * (self.w[8].exp()
+                * (11.0 - difficulty)
+                * stability.powf(-self.w[9])
+                * ((self.w[10] * (1.0 - r)).exp() - 1.0)
+                * hard_penalty
+                * easy_bonus
+                + 1.0)
+    }
+
+    fn next_forget_stability(&self, difficulty: f64, stability: f64, r: f64) -> f64 {
+        self.w[11]
+            * difficulty.powf(-self.w[12])
+            * ((stability + 1.0).powf(self.w[13]) - 1.0)
+            * (self.w[14] * (1.0 - r)).exp()
+    }
+
+    /// Replays a card's review history and returns its (stability, difficulty)
+    pub fn memory_state(&self, history: &[ReviewLog]) -> Option<(f64, f64)> {
+        let first = history.first()?;
+        let mut stability = self.w[first.grade.rating() - 1];
+        let mut difficulty = self.initial_difficulty(first.grade);
+        let mut last_time = first.time;
+
+        for review in &history[1..] {
+            let elapsed_days = review.time.saturating_sub(last_time) as f64 / SECONDS_PER_DAY as f64;
+            let r = Self::retrievability(elapsed_days, stability);
+
+            stability = if review.grade == Grade::Again {
+                self.next_forget_stability(difficulty, stability, r)
+            } else {
+                self.next_recall_stability(difficulty, stability, r, review.grade)
+            };
+            difficulty = self.next_difficulty(difficulty, review.grade);
+            last_time = review.time;
+        }
+
+        Some((stability.max(0.01), difficulty))
+    }
+
+    /// Days until recall probability decays to the target retention
+    fn next_interval(&self, stability: f64) -> u32 {
+        let days = stability / FACTOR * (self.target_retention.powf(1.0 / DECAY) - 1.0);
+        days.round().clamp(1.0, 36500.0) as u32
+    }
+}
+
+impl Scheduler for Fsrs {
+    fn name(&self) -> &str {
+        "FSRS"
+    }
+
+    fn schedule(&self, state: &mut ReviewState, _grade: Grade, now: u64) {
+        if let Some((stability, _)) = self.memory_state(&state.history) {
+            state.interval = self.next_interval(stability);
+            state.due = now + state.interval as u64 * SECONDS_PER_DAY;
+        }
+    }
+}
+
+/// Default number of days between reviews for each Leitner box
+const DEFAULT_LEITNER_CADENCE: [u32; 5] = [1, 2, 4, 8, 16];
+
+/// Leitner boxes: correct answers move a card up one box, misses send it back to box 1
+pub struct Leitner {
+    cadence: Vec<u32>, // Days between reviews for box 1..N
+}
+
+impl Leitner {
+    /// Reads the box cadence from the `leitner_cadence` setting (e.g. `1, 3, 7, 14`)
+    pub fn from_settings(settings: &DeckSettings) -> Self {
+        let cadence = settings
+            .get_str("leitner_cadence")
+            .and_then(|value| {
+                value
+                    .split(',')
+                    .map(|days| days.trim().parse::<u32>().ok())
+                    .collect::<Option<Vec<_>>>()
+            })
+            .filter(|cadence| !cadence.is_empty());
+
+        match cadence {
+            Some(cadence) => Leitner { cadence },
+            None => {
+                if settings.get_str("leitner_cadence").is_some() {
+                    eprintln!("Warning: leitner_cadence expects a list of days like '1, 2, 4'");
+                }
+                Leitner {
+                    cadence: DEFAULT_LEITNER_CADENCE.to_vec(),
+                }
+            }
+        }
+    }
+
+    fn box_count(&self) -> u32 {
+        self.cadence.len() as u32
+    }
+}
+
+impl Scheduler for Leitner {
+    fn name(&self) -> &str {
+        "Leitner"
+    }
+
+    fn schedule(&self, state: &mut ReviewState, grade: Grade, now: u64) {
+        state.leitner_box = if grade.is_pass() {
+            (state.leitner_box + 1).min(self.box_count())
+        } else {
+            1
+        };
+
+        state.interval = self.cadence[state.leitner_box as usize - 1];
+        state.due = now + state.interval as u64 * SECONDS_PER_DAY;
+    }
+
+    /// Lower boxes hold the cards we know least, so they come first
+    fn queue_order(&self, state: &ReviewState) -> u32 {
+        state.leitner_box
+    }
+
+    fn card_label(&self, state: &ReviewState) -> Option<String> {
+        Some(format!("Box {} / {}", state.leitner_box.min(self.box_count()), self.box_count()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    #[test]
+    fn study_days_roll_over_at_the_local_hour() {
+        let hour = 60 * 60;
+        // 2024-03-10 00:00 UTC
+        let midnight = 1_710_028_800;
+
+        assert_eq!(study_day_in(&Utc, midnight + 5 * hour, 4), (midnight + 4 * hour, midnight + 28 * hour));
+        assert_eq!(study_day_in(&Utc, midnight + 3 * hour, 4), (midnight - 20 * hour, midnight + 4 * hour));
+
+        // 03:00 UTC is 05:00 in UTC+2, past a 4 am rollover that was at 02:00 UTC
+        let plus_two = FixedOffset::east_opt(2 * hour as i32).unwrap();
+        assert_eq!(study_day_in(&plus_two, midnight + 3 * hour, 4), (midnight + 2 * hour, midnight + 26 * hour));
+        // 01:00 UTC is 03:00 there, still the day before
+        assert_eq!(study_day_in(&plus_two, midnight + hour, 4).0, midnight - 22 * hour);
+
+        // 23:00 UTC is 19:00 in UTC-4, a midnight rollover was at 04:00 UTC
+        let minus_four = FixedOffset::west_opt(4 * hour as i32).unwrap();
+        assert_eq!(study_day_in(&minus_four, midnight + 23 * hour, 0), (midnight + 4 * hour, midnight + 28 * hour));
+    }
+
+    /// Grades a card on the day it comes due, the way `ReviewState::record`
+    /// does for a graduated card, and returns the new interval
+    fn review(scheduler: &dyn Scheduler, state: &mut ReviewState, grade: Grade) -> u32 {
+        let now = state.due.max(SECONDS_PER_DAY);
+        state.history.push(ReviewLog { time: now, grade });
+        scheduler.schedule(state, grade, now);
+        assert_eq!(state.due, now + state.interval as u64 * SECONDS_PER_DAY);
+        state.interval
+    }
+
+    fn intervals(scheduler: &dyn Scheduler, grades: &[Grade]) -> Vec<u32> {
+        let mut state = ReviewState::default();
+        grades.iter().map(|&grade| review(scheduler, &mut state, grade)).collect()
+    }
+
+    #[test]
+    fn sm2_intervals_grow_by_the_ease() {
+        use Grade::*;
+        // Good keeps the ease at 2.5; Again drops it to 1.96 and starts over
+        assert_eq!(intervals(&Sm2, &[Good, Good, Good, Again, Good, Good, Good]), [1, 6, 15, 1, 1, 6, 12]);
+    }
+
+    #[test]
+    fn sm2_ease_changes_with_the_grade() {
+        let mut state = ReviewState::default();
+        review(&Sm2, &mut state, Grade::Easy);
+        assert!((state.ease_factor - 2.6).abs() < 1e-9);
+        assert_eq!(state.repetitions, 1);
+
+        // Repeated lapses stop at the 1.3 floor
+        for _ in 0..5 {
+            review(&Sm2, &mut state, Grade::Again);
+        }
+        assert_eq!(state.ease_factor, 1.3);
+        assert_eq!((state.repetitions, state.interval), (0, 1));
+    }
+
+    #[test]
+    fn fsrs_first_interval_is_the_initial_stability() {
+        // At 90% retention the interval in days equals the stability
+        let fsrs = Fsrs::new(0.9);
+        let first: Vec<u32> = Grade::ALL.iter().map(|&grade| intervals(&fsrs, &[grade])[0]).collect();
+        assert_eq!(first, [1, 1, 4, 14]);
+    }
+
+    #[test]
+    fn fsrs_intervals_grow_on_passes_and_shrink_on_a_lapse() {
+        use Grade::*;
+        let fsrs = Fsrs::new(0.9);
+        let days = intervals(&fsrs, &[Good, Good, Good, Good, Again]);
+        assert!(days.windows(2).take(3).all(|pair| pair[1] > pair[0]), "{:?}", days);
+        assert!(days[4] < days[3], "{:?}", days);
+
+        // A higher target retention brings the card back sooner
+        let strict = intervals(&Fsrs::new(0.95), &[Good, Good, Good]);
+        assert!(strict[2] < days[2], "{:?} vs {:?}", strict, days);
+    }
+
+    #[test]
+    fn leitner_moves_cards_between_boxes() {
+        use Grade::*;
+        let leitner = Leitner {
+            cadence: DEFAULT_LEITNER_CADENCE.to_vec(),
+        };
+        // Passes climb a box each, the top box holds, and a miss goes back to box 1
+        let days = intervals(&leitner, &[Good, Hard, Easy, Good, Good, Good, Again, Good]);
+        assert_eq!(days, [2, 4, 8, 16, 16, 16, 1, 2]);
+
+        let mut state = ReviewState::default();
+        review(&leitner, &mut state, Good);
+        assert_eq!(leitner.card_label(&state).as_deref(), Some("Box 2 / 5"));
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// File holding defaults shared by every deck in the folder
+const FOLDER_SETTINGS_FILE: &str = "default.settings";
+
+/// Study options for a deck, read from simple `key = value` files.
+///
+/// Values in `<folder>/default.settings` apply to every deck and can be
+/// overridden by a `<deck>.settings` file next to the deck itself.
+/// Lines starting with `#` are comments.
+pub struct DeckSettings {
+    values: HashMap<String, String>,
+}
+
+impl DeckSettings {
+    /// Loads the settings that apply to the deck at `deck_path`
+    pub fn load(deck_path: &str) -> Self {
+        let path = Path::new(deck_path);
+        let mut values = HashMap::new();
+
+        if let Some(folder) = path.parent() {
+            read_settings_file(&folder.join(FOLDER_SETTINGS_FILE), &mut values);
+        }
+        read_settings_file(&path.with_extension("settings"), &mut values);
+
+        DeckSettings { values }
+    }
+
+    /// Returns the raw value of a setting, if present
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+
+    /// Returns a numeric setting, falling back to `default` when missing or invalid
+    pub fn get_f64(&self, key: &str, default: f64) -> f64 {
+        match self.get_str(key) {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                eprintln!("Warning: setting '{}' expects a number, got '{}'", key, value);
+                default
+            }),
+            None => default,
+        }
+    }
+
+    /// Returns a whole-number setting, falling back to `default` when missing or invalid
+    pub fn get_usize(&self, key: &str, default: usize) -> usize {
+        match self.get_str(key) {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                eprintln!("Warning: setting '{}' expects a whole number, got '{}'", key, value);
+                default
+            }),
+            None => default,
+        }
+    }
+
+    /// Returns a true/false setting, falling back to `default` when missing or invalid
+    pub fn get_bool(&self, key: &str, default: bool) -> bool {
+        match self.get_str(key).map(|v| v.to_lowercase()) {
+            Some(value) => match value.as_str() {
+                "true" | "yes" | "on" | "1" => true,
+                "false" | "no" | "off" | "0" => false,
+                _ => {
+                    eprintln!("Warning: setting '{}' expects true or false, got '{}'", key, value);
+                    default
+                }
+            },
+            None => default,
+        }
+    }
+}
+
+/// Reads `key = value` lines from `path` into `values`; missing files are ignored
+fn read_settings_file(path: &Path, values: &mut HashMap<String, String>) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        // Skip blank lines and comments
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.split_once('=') {
+            Some((key, value)) => {
+                values.insert(key.trim().to_lowercase(), value.trim().to_string());
+            }
+            None => eprintln!(
+                "Warning: {}:{}: expected 'key = value'",
+                path.display(),
+                line_number + 1
+            ),
+        }
+    }
+}
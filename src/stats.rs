@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+
+use crate::scheduler::Grade;
+
+/// One answer given during a session
+struct Answer {
+    card: usize,          // Index of the card in the game's card list
+    grade: Grade,
+    time_taken: Duration, // From showing the card to grading it
+}
+
+/// What happened during a study session, for the summary screen
+pub struct SessionStats {
+    answers: Vec<Answer>,
+    started: Instant,
+    finished: Option<Instant>,
+}
+
+impl SessionStats {
+    /// Starts the clock on a new session
+    pub fn start() -> Self {
+        SessionStats {
+            answers: Vec::new(),
+            started: Instant::now(),
+            finished: None,
+        }
+    }
+
+    /// Logs one graded card and how long it took to answer
+    pub fn record(&mut self, card: usize, grade: Grade, time_taken: Duration) {
+        self.answers.push(Answer {
+            card,
+            grade,
+            time_taken,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.answers.is_empty()
+    }
+
+    /// Stops the clock; `elapsed` keeps returning the time up to now
+    pub fn finish(&mut self) {
+        self.finished.get_or_insert_with(Instant::now);
+    }
+
+    /// Picks the clock up again after going back from the summary
+    pub fn resume(&mut self) {
+        if let Some(finished) = self.finished.take() {
+            self.started += finished.elapsed();
+        }
+    }
+
+    /// Time spent in the session
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now) - self.started
+    }
+
+    /// Number of different cards answered
+    pub fn cards_seen(&self) -> usize {
+        let mut cards: Vec<usize> = self.answers.iter().map(|answer| answer.card).collect();
+        cards.sort();
+        cards.dedup();
+        cards.len()
+    }
+
+    /// Answers given with each grade, in the order of `Grade::ALL`
+    pub fn grade_counts(&self) -> [usize; 4] {
+        let mut counts = [0; 4];
+        for answer in &self.answers {
+            counts[answer.grade.rating() - 1] += 1;
+        }
+        counts
+    }
+
+    /// Total number of answers, counting repeats of the same card
+    pub fn answer_count(&self) -> usize {
+        self.answers.len()
+    }
+
+    /// Cards that took longest to answer, slowest first, with their longest time
+    pub fn slowest(&self, count: usize) -> Vec<(usize, Duration)> {
+        let mut slowest: Vec<(usize, Duration)> = Vec::new();
+        for answer in &self.answers {
+            match slowest.iter_mut().find(|(card, _)| *card == answer.card) {
+                Some((_, time)) => *time = (*time).max(answer.time_taken),
+                None => slowest.push((answer.card, answer.time_taken)),
+            }
+        }
+        slowest.sort_by_key(|&(_, time)| std::cmp::Reverse(time));
+        slowest.truncate(count);
+        slowest
+    }
+
+    /// Cards graded Again at least once, in the order they were first missed
+    pub fn missed(&self) -> Vec<usize> {
+        let mut missed = Vec::new();
+        for answer in self.answers.iter().filter(|answer| !answer.grade.is_pass()) {
+            if !missed.contains(&answer.card) {
+                missed.push(answer.card);
+            }
+        }
+        missed
+    }
+}
@@ -1,14 +1,18 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// File extensions read as decks: comma- or tab-separated, or plain text exports
+const DECK_EXTENSIONS: [&str; 3] = ["csv", "tsv", "txt"];
+
 pub struct DeckManager {
     deck_files: Vec<String>,      // List of all deck filenames
     current_deck_index: usize,    // Which deck is currently active
     deck_folder: String,          // Path to the folder
+    selected: Vec<usize>,         // Decks mixed into one session, empty to study the current deck alone
 }
 
 impl DeckManager {
-    /// Creates a new DeckManager by reading all CSV files from the specified folder
+    /// Creates a new DeckManager by reading all deck files from the specified folder
     pub fn new(folder: &str) -> Result<Self, std::io::Error> {
         let path = Path::new(folder);
         
@@ -27,17 +31,17 @@ impl DeckManager {
             ));
         }
 
-        // Read all .csv files from the directory
+        // Read all deck files from the directory
         let mut deck_files = Vec::new();
         
         for entry in fs::read_dir(path)? {
             let entry = entry?;
             let file_path = entry.path();
             
-            // Only include .csv files
+            // Only include files with a deck extension
             if file_path.is_file() {
                 if let Some(extension) = file_path.extension() {
-                    if extension == "csv" {
+                    if DECK_EXTENSIONS.iter().any(|deck_extension| extension == *deck_extension) {
                         if let Some(filename) = file_path.file_name() {
                             if let Some(filename_str) = filename.to_str() {
                                 deck_files.push(filename_str.to_string());
@@ -51,11 +55,33 @@ impl DeckManager {
         // Sort alphabetically for consistent ordering
         deck_files.sort();
 
+        // A deck's progress and settings files are named after the deck, so two
+        // files with the same name (say vocab.csv and vocab.tsv) can't both be used
+        let mut kept: Vec<String> = Vec::new();
+        deck_files.retain(|file| {
+            match kept.iter().find(|other| deck_name(other) == deck_name(file)) {
+                Some(other) => {
+                    eprintln!(
+                        "Warning: {}/{}: skipped, deck name '{}' is already used by {}",
+                        folder,
+                        file,
+                        deck_name(file),
+                        other
+                    );
+                    false
+                }
+                None => {
+                    kept.push(file.clone());
+                    true
+                }
+            }
+        });
+
         // Check if any decks were found
         if deck_files.is_empty() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
-                format!("No CSV files found in '{}'", folder)
+                format!("No deck files (.csv, .tsv, .txt) found in '{}'", folder)
             ));
         }
 
@@ -63,13 +89,94 @@ impl DeckManager {
             deck_files,
             current_deck_index: 0,
             deck_folder: folder.to_string(),
+            selected: Vec::new(),
         })
     }
 
-    /// Returns the full path to the current deck file
-    pub fn get_current_deck_path(&self) -> String {
-        let filename = &self.deck_files[self.current_deck_index];
-        format!("{}/{}", self.deck_folder, filename)
+    /// Returns the full path to the deck file at `index`
+    fn deck_path(&self, index: usize) -> String {
+        format!("{}/{}", self.deck_folder, self.deck_files[index])
+    }
+
+    /// Adds the current deck to the mixed session, or takes it out if it is already in
+    pub fn toggle_current_deck(&mut self) {
+        match self.selected.iter().position(|&i| i == self.current_deck_index) {
+            Some(position) => {
+                self.selected.remove(position);
+            }
+            None => {
+                self.selected.push(self.current_deck_index);
+                self.selected.sort();
+            }
+        }
+    }
+
+    /// Mixes every deck into one session, or goes back to the current deck
+    /// alone if they already are
+    pub fn toggle_all_decks(&mut self) {
+        if self.all_selected() {
+            self.selected.clear();
+        } else {
+            self.selected = (0..self.deck_files.len()).collect();
+        }
+    }
+
+    /// Drops the mixed selection and goes back to studying the current deck alone
+    pub fn clear_selection(&mut self) {
+        self.selected.clear();
+    }
+
+    /// Selects decks by name (without extension) for a mixed session; `all` selects every deck
+    pub fn select_decks(&mut self, names: &[String]) -> Result<(), String> {
+        if names.iter().any(|name| name == "all") {
+            self.selected = (0..self.deck_files.len()).collect();
+            return Ok(());
+        }
+
+        let mut selected = Vec::new();
+        for name in names {
+            let index = self
+                .deck_files
+                .iter()
+                .position(|file| deck_name(file) == name)
+                .ok_or_else(|| format!("no deck named '{}'", name))?;
+            if !selected.contains(&index) {
+                selected.push(index);
+            }
+        }
+        selected.sort();
+        self.selected = selected;
+        Ok(())
+    }
+
+    /// Returns true if every deck is selected
+    pub fn all_selected(&self) -> bool {
+        self.selected.len() == self.deck_files.len()
+    }
+
+    /// Returns true if decks were picked for the session with X or --decks,
+    /// rather than studying the deck A/D points at
+    pub fn has_selection(&self) -> bool {
+        !self.selected.is_empty()
+    }
+
+    /// Returns true if several decks are studied together
+    pub fn is_mixed(&self) -> bool {
+        self.selected.len() > 1
+    }
+
+    /// Returns the decks studied together as (name, path) pairs: the mixed
+    /// selection, or just the current deck when nothing is selected
+    pub fn session_decks(&self) -> Vec<(String, String)> {
+        let indices = if self.selected.is_empty() {
+            vec![self.current_deck_index]
+        } else {
+            self.selected.clone()
+        };
+        indices
+            .into_iter()
+            .map(|i| (deck_name(&self.deck_files[i]).to_string(), self.deck_path(i)))
+            .collect()
     }
 
     /// Cycles to the next deck (wraps around to the beginning)
@@ -90,16 +197,11 @@ impl DeckManager {
         }
     }
 
-    /// Returns the current deck name without path or extension
-    pub fn get_current_deck_name(&self) -> &str {
-        let filename = &self.deck_files[self.current_deck_index];
-        
-        // Remove .csv extension if present
-        if let Some(name_without_ext) = filename.strip_suffix(".csv") {
-            name_without_ext
-        } else {
-            filename
-        }
+    /// Returns the name of the deck A/D points at, which X adds or takes out,
+    /// and whether it is in the selection
+    pub fn current_deck(&self) -> (&str, bool) {
+        let name = deck_name(&self.deck_files[self.current_deck_index]);
+        (name, self.selected.contains(&self.current_deck_index))
     }
 
     /// Returns a formatted string showing current deck position (e.g., "Deck 2/5")
@@ -120,7 +222,7 @@ impl DeckManager {
     /// Formats the deck name to be more human-readable
     /// Converts underscores to spaces and capitalizes words
     pub fn get_formatted_deck_name(&self) -> String {
-        let name = self.get_current_deck_name();
+        let (name, _) = self.current_deck();
         
         // Replace underscores with spaces
         let with_spaces = name.replace('_', " ");
@@ -139,3 +241,27 @@ impl DeckManager {
             .join(" ")
     }
 }
+
+/// Returns a deck's name: its filename without the extension
+fn deck_name(filename: &str) -> &str {
+    filename.rsplit_once('.').map_or(filename, |(name, _)| name)
+}
+
+/// Number of single-character insertions, deletions or substitutions
+/// needed to turn `a` into `b`
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+    let mut current = vec![0; b_chars.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, &b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + if a_char == b_char { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b_chars.len()]
+}